@@ -1,81 +1,254 @@
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use csv_validator_core::{FieldCountValidator, IllegalCharactersValidator, LineLengthValidator, Validator};
-use std::sync::Arc;
-use crate::CliArgs;
+use std::path::{Path, PathBuf};
+use csv_validator_core::{CommonSettings, Dialect, Severity, ValidatorSpec, into_validators};
 
-#[derive(Debug, Deserialize)]
+use crate::lint::{self, Finding};
+
+/// The validators are the core's `ValidatorSpec`, so every core validator and option can be configured here.
+/// `common` holds the settings of all validators, a validator's own `common` overrides them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ConfigFile {
-    pub common: Option<CommonConfig>,
+    /// config files this one builds on, relative to it: their settings come first, this file's override them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// settings of all validators: separator, quote_char and has_header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub common: Option<CommonSettings>,
+    /// the validators to run, all in one pass over the file
+    #[serde(default)]
     pub validators: Vec<ValidatorSpec>,
+    /// validators to enable or disable, by type or id, picked with --profile
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct CommonConfig {
-    pub quote_char: Option<char>,
-    pub separator: Option<char>,
-    pub has_header: Option<bool>,
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// validator types or ids to run, even when disabled in the config
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enable: Vec<String>,
+    /// validator types or ids not to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(tag = "type")]
-pub enum ValidatorSpec {
-    #[serde(rename = "illegal_chars")]
-    IllegalChars {
-        illegal_chars: Vec<String>,
-        replace_with: Vec<String>,
-        fix: bool,
-        enabled: bool,
-        common: Option<CommonConfig>,
-    },
-    #[serde(rename = "field_count")]
-    FieldCount {
-        expected: usize,
-        enabled: bool,
-        common: Option<CommonConfig>,
-    },
+impl ConfigFile {
+    /// This config on top of `base`: its common settings over those of `base`, its validators replacing those
+    /// with the same `id`, or added after them, and its profiles replacing those of the same name.
+    fn over(self, base: ConfigFile) -> ConfigFile {
+        let mut profiles = base.profiles;
+        profiles.extend(self.profiles);
+        ConfigFile {
+            include: Vec::new(),
+            common: match (self.common, base.common) {
+                (Some(own), Some(base)) => Some(own.or(&base)),
+                (own, base) => own.or(base),
+            },
+            validators: merge_validators(base.validators, self.validators),
+            profiles,
+        }
+    }
+
+    /// The validators with the profile's `enable` and `disable` applied, each entry picking the validator with
+    /// that `id`, or else all of that type.
+    fn with_profile(mut self, name: &str) -> anyhow::Result<ConfigFile> {
+        let Some(profile) = self.profiles.get(name) else {
+            let names: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            anyhow::bail!("no profile '{}' in the config, it has: {}", name, names.join(", "));
+        };
+        for (picked, enabled) in profile.enable.iter().map(|picked| (picked, true)).chain(profile.disable.iter().map(|picked| (picked, false))) {
+            let by_id = self.validators.iter().any(|spec| spec.id() == Some(picked));
+            let is_picked = |spec: &ValidatorSpec| if by_id { spec.id() == Some(picked) } else { spec.type_name() == picked };
+            anyhow::ensure!(
+                self.validators.iter().any(is_picked),
+                "profile '{}' {} {}, but there is no validator with that id or type",
+                name,
+                if enabled { "enables" } else { "disables" },
+                picked
+            );
+            self.validators =
+                self.validators.into_iter().map(|spec| if is_picked(&spec) { spec.with_enabled(enabled) } else { spec }).collect();
+        }
+        Ok(self)
+    }
 }
 
+/// A config file as read, after the environment variables are filled in.
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+}
 
+/// The config file and all the files it includes, included files first, each one once.
+pub fn source_files(path: &str) -> anyhow::Result<Vec<SourceFile>> {
+    fn visit(path: PathBuf, including: &mut Vec<PathBuf>, files: &mut Vec<SourceFile>) -> anyhow::Result<()> {
+        let canonical = fs::canonicalize(&path).map_err(|e| anyhow::anyhow!("can't read config {}: {}", path.display(), e))?;
+        if let Some(first) = including.iter().position(|file| *file == canonical) {
+            let cycle: Vec<_> = including[first..].iter().chain([&canonical]).map(|file| file.display().to_string()).collect();
+            anyhow::bail!("config files include each other: {}", cycle.join(" -> "));
+        }
+        if files.iter().any(|file| fs::canonicalize(&file.path).ok().as_ref() == Some(&canonical)) {
+            return Ok(());
+        }
 
-pub fn load_config(path: String) -> anyhow::Result<ConfigFile> {
-    let content = fs::read_to_string(path)?;
-    let config: ConfigFile = serde_yaml::from_str(&content)?;
-    Ok(config)
+        let source = interpolate(&fs::read_to_string(&path)?).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        // only the includes, checking the rest is up to the linter
+        #[derive(Deserialize)]
+        struct Includes {
+            #[serde(default)]
+            include: Vec<String>,
+        }
+        let includes = serde_yaml::from_str::<Includes>(&source).map(|i| i.include).unwrap_or_default();
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        including.push(canonical);
+        for include in includes {
+            visit(dir.join(include), including, files)?;
+        }
+        including.pop();
+        files.push(SourceFile { path, source });
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    visit(PathBuf::from(path), &mut Vec::new(), &mut files)?;
+    Ok(files)
 }
 
-pub fn build_validators_from_config(config: ConfigFile) -> Vec<Box<dyn Validator>> {
-    let mut out = Vec::new();
+/// The config with its includes resolved and the profile, if any, applied.
+/// Fails with all the errors `check-config` finds, not only the first one.
+pub fn load_config(path: &str, profile: Option<&str>) -> anyhow::Result<ConfigFile> {
+    let mut config = ConfigFile::default();
+    for file in source_files(path)? {
+        let errors: Vec<_> = lint::check_config(&file.source)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| format!("{}:{}", file.path.display(), finding))
+            .collect();
+        anyhow::ensure!(errors.is_empty(), "invalid config {}:\n{}", file.path.display(), errors.join("\n"));
+        let file_config: ConfigFile = serde_yaml::from_str(&file.source)
+            .map_err(|e| anyhow::anyhow!("invalid config {}: {}", file.path.display(), e))?;
+        config = file_config.over(config);
+    }
+    match profile {
+        Some(name) => config.with_profile(name),
+        None => Ok(config),
+    }
+}
+
+/// The findings of the config file and of the files it includes, by file.
+pub fn check_config(path: &str) -> anyhow::Result<Vec<(PathBuf, Vec<Finding>)>> {
+    Ok(source_files(path)?.into_iter().map(|file| (file.path, lint::check_config(&file.source))).collect())
+}
+
+/// The errors the config only has once its includes are resolved, ie. conflicting common settings,
+/// without and with each profile.
+pub fn check_resolved(path: &str) -> anyhow::Result<Vec<String>> {
+    let config = load_config(path, None)?;
+    let mut errors = Vec::new();
+    for profile in [None].into_iter().chain(config.profiles.keys().map(Some)) {
+        let check = |config: ConfigFile| -> anyhow::Result<()> {
+            let dialect = Dialect::resolve(&CommonSettings::default(), &config.common.unwrap_or_default(), &config.validators)?;
+            into_validators(config.validators.into_iter().map(lint::without_references), &dialect)?;
+            Ok(())
+        };
+        let resolved = match profile {
+            Some(name) => config.clone().with_profile(name),
+            None => Ok(config.clone()),
+        };
+        if let Err(e) = resolved.and_then(check) {
+            errors.push(match profile {
+                Some(name) => format!("with profile '{}': {}", name, e),
+                None => e.to_string(),
+            });
+        }
+    }
+    Ok(errors)
+}
 
-    for spec in config.validators {
-        match spec {
-            ValidatorSpec::IllegalChars {
-                illegal_chars,
-                enabled,
-                ..
-            } if enabled => {
-                out.push(Box::new(IllegalCharactersValidator::new(
-                    &illegal_chars.iter().map(AsRef::as_ref).collect::<Vec<_>>(),
-                ))as Box<dyn Validator>);
+/// Replaces `${NAME}` with the environment variable, `${NAME:-default}` with the default when it isn't set or empty.
+/// `$${` is a literal `${`, comment lines are left as they are. Fails on the variables that aren't set and have
+/// no default, listing them all.
+pub fn interpolate(source: &str) -> anyhow::Result<String> {
+    let mut result = String::with_capacity(source.len());
+    let mut missing = Vec::new();
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        if line.trim_start().starts_with('#') {
+            result.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                result.push_str(&rest[..start - 1]);
+                result.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
             }
-            ValidatorSpec::FieldCount {
-                expected,
-                enabled,
-                common,
-            } if enabled => {
-                let sep = common
-                    .as_ref()
-                    .and_then(|c| c.separator)
-                    .unwrap_or(';') as u8;
-                out.push(Box::new(FieldCountValidator::new(
-                    expected,
-                    sep,
-                )) as Box<dyn Validator>);
+            result.push_str(&rest[..start]);
+            let end = rest[start..].find('}').ok_or_else(|| anyhow::anyhow!("line {}: unclosed ${{", i + 1))?;
+            let reference = &rest[start + 2..start + end];
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            anyhow::ensure!(
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+                "line {}: invalid environment variable name '{}'",
+                i + 1,
+                name
+            );
+            match (std::env::var(name).ok().filter(|value| !value.is_empty()), default) {
+                (Some(value), _) => result.push_str(&value),
+                (None, Some(default)) => result.push_str(default),
+                (None, None) => missing.push(format!("{} (line {})", name, i + 1)),
             }
-            _ => {}
+            rest = &rest[start + end + 1..];
         }
+        result.push_str(rest);
     }
+    anyhow::ensure!(missing.is_empty(), "environment variables not set, without default: {}", missing.join(", "));
+    Ok(result)
+}
 
-    out
+/// The validators of a config with those of the config on top of it: each of the `overrides` with an `id` replaces
+/// the validator with that `id`, at its position, the others are added after them.
+pub fn merge_validators(
+    validators: Vec<ValidatorSpec>,
+    overrides: impl IntoIterator<Item = ValidatorSpec>,
+) -> Vec<ValidatorSpec> {
+    let mut merged = validators;
+    for spec in overrides {
+        match merged.iter_mut().find(|merged| merged.id().is_some() && merged.id() == spec.id()) {
+            Some(replaced) => *replaced = spec,
+            None => merged.push(spec),
+        }
+    }
+    merged
 }
 
+/// The validators, with the `overrides` replacing all those of the same type, at the position of the first one,
+/// or added after them: the validators of the flags, a table schema or CSVW metadata, which have no `id`.
+pub fn replace_validators(
+    validators: Vec<ValidatorSpec>,
+    overrides: impl IntoIterator<Item = ValidatorSpec>,
+) -> Vec<ValidatorSpec> {
+    let mut overrides: Vec<_> = overrides.into_iter().map(Some).collect();
+    let replaced: HashSet<_> = overrides.iter().flatten().map(ValidatorSpec::type_name).collect();
+    let mut merged = Vec::with_capacity(validators.len() + overrides.len());
+    for spec in validators {
+        let kind = spec.type_name();
+        if !replaced.contains(kind) {
+            merged.push(spec);
+        } else {
+            // only the first of that type takes the overrides, there are none left for the others
+            merged.extend(overrides.iter_mut().filter_map(|o| o.take_if(|o| o.type_name() == kind)));
+        }
+    }
+    merged.extend(overrides.into_iter().flatten());
+    merged
+}
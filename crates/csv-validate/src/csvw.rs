@@ -0,0 +1,476 @@
+use csv_validator_core::{Column, ColumnType, CommonSettings, ForeignKey, Severity, ValidatorSpec};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::table_schema::{text, Names};
+
+/// CSVW metadata: a table, or a group of tables of which one describes the validated file.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TableGroup {
+    #[serde(default)]
+    tables: Vec<Table>,
+    #[serde(default)]
+    dialect: Option<Dialect>,
+    #[serde(flatten)]
+    inherited: Inherited,
+    /// the properties of a single table, when there is no `tables`
+    #[serde(flatten)]
+    table: Table,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Table {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    dialect: Option<Dialect>,
+    #[serde(default)]
+    table_schema: Option<SchemaOrPath>,
+    #[serde(flatten)]
+    inherited: Inherited,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SchemaOrPath {
+    Path(String),
+    Schema(Schema),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Schema {
+    #[serde(default)]
+    columns: Vec<SchemaColumn>,
+    #[serde(default)]
+    primary_key: Option<Names>,
+    #[serde(default)]
+    foreign_keys: Vec<SchemaForeignKey>,
+    #[serde(flatten)]
+    inherited: Inherited,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaColumn {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    titles: Option<Titles>,
+    /// not in the file: only there to describe the data
+    #[serde(default, rename = "virtual")]
+    is_virtual: bool,
+    /// the values are lists, split on this
+    #[serde(default)]
+    separator: Option<String>,
+    #[serde(flatten)]
+    inherited: Inherited,
+}
+
+/// The properties a column inherits from its schema, table and table group.
+#[derive(Clone, Default, Deserialize)]
+struct Inherited {
+    #[serde(default)]
+    null: Option<Names>,
+    #[serde(default)]
+    required: Option<bool>,
+    #[serde(default)]
+    datatype: Option<Datatype>,
+}
+
+impl Inherited {
+    fn or(&self, fallback: &Inherited) -> Inherited {
+        Inherited {
+            null: self.null.clone().or_else(|| fallback.null.clone()),
+            required: self.required.or(fallback.required),
+            datatype: self.datatype.clone().or_else(|| fallback.datatype.clone()),
+        }
+    }
+}
+
+/// Titles, in one language or per language.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Titles {
+    Names(Names),
+    Languages(BTreeMap<String, Names>),
+}
+
+impl Titles {
+    fn to_vec(&self) -> Vec<String> {
+        match self {
+            Titles::Names(names) => names.to_vec(),
+            Titles::Languages(languages) => languages.values().flat_map(Names::to_vec).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum Datatype {
+    Base(String),
+    Described(Box<DatatypeDescription>),
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DatatypeDescription {
+    #[serde(default = "string_base")]
+    base: String,
+    #[serde(default)]
+    format: Option<Value>,
+    #[serde(default, alias = "minInclusive")]
+    minimum: Option<Value>,
+    #[serde(default, alias = "maxInclusive")]
+    maximum: Option<Value>,
+    #[serde(default)]
+    min_exclusive: Option<Value>,
+    #[serde(default)]
+    max_exclusive: Option<Value>,
+    #[serde(default)]
+    length: Option<usize>,
+    #[serde(default)]
+    min_length: Option<usize>,
+    #[serde(default)]
+    max_length: Option<usize>,
+}
+
+fn string_base() -> String {
+    "string".to_string()
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Dialect {
+    #[serde(default)]
+    delimiter: Option<char>,
+    #[serde(default)]
+    quote_char: Option<Value>,
+    #[serde(default)]
+    header: Option<bool>,
+    #[serde(default)]
+    header_row_count: Option<usize>,
+    #[serde(default)]
+    skip_rows: Option<usize>,
+    /// a string or a list of them, `["\r\n", "\n"]` by default
+    #[serde(default)]
+    line_terminators: Option<Value>,
+    /// the other properties, only checked for those the reader doesn't support
+    #[serde(flatten)]
+    options: serde_json::Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaForeignKey {
+    column_reference: Names,
+    reference: Reference,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reference {
+    #[serde(default)]
+    resource: Option<String>,
+    #[serde(default)]
+    schema_reference: Option<String>,
+    column_reference: Names,
+}
+
+/// What the metadata says about the validated file.
+pub struct Metadata {
+    /// the dialect: delimiter, quote, header and skipped rows
+    pub common: CommonSettings,
+    /// the number of fields, and a columns validator checking the header, the datatypes and constraints of the
+    /// columns, the primary key and the foreign keys
+    pub validators: Vec<ValidatorSpec>,
+    /// what the validators don't check: other datatypes and formats are read as strings, other dialect
+    /// options are ignored
+    pub warnings: Vec<String>,
+}
+
+/// The metadata file next to `input`: `<input>-metadata.json`, or else `csv-metadata.json` in its directory.
+pub fn metadata_path(input: &str) -> anyhow::Result<PathBuf> {
+    let sibling = PathBuf::from(format!("{}-metadata.json", input));
+    let directory = Path::new(input).with_file_name("csv-metadata.json");
+    [sibling, directory].into_iter().find(|path| path.is_file()).ok_or_else(|| {
+        anyhow::anyhow!("no CSVW metadata for {}: no {}-metadata.json, nor csv-metadata.json next to it", input, input)
+    })
+}
+
+/// Dialect options the reader only supports with their default value.
+fn unsupported_options() -> [(&'static str, Value); 7] {
+    [
+        ("commentPrefix", Value::Null),
+        ("doubleQuote", json!(true)),
+        ("encoding", json!("utf-8")),
+        ("skipBlankRows", json!(false)),
+        ("skipColumns", json!(0)),
+        ("skipInitialSpace", json!(false)),
+        ("trim", json!(false)),
+    ]
+}
+
+/// The dialect and validators of the CSVW metadata at `path`, for the file at `input`. In a table group, the table
+/// whose `url` is `input` describes it, or the only table. Columns are reported by their `name`, the header is
+/// checked against their `titles`; with several header rows, against the last one.
+///
+/// Table urls, resources and schema references are paths relative to the metadata file.
+pub fn load(path: &Path, input: Option<&str>) -> anyhow::Result<Metadata> {
+    let invalid = |e: &dyn std::fmt::Display| anyhow::anyhow!("invalid CSVW metadata {}: {}", path.display(), e);
+    let source = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("can't read CSVW metadata {}: {}", path.display(), e))?;
+    let group: TableGroup = serde_json::from_str(&source).map_err(|e| invalid(&e))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let TableGroup { tables, dialect: group_dialect, inherited: group_inherited, table: single } = group;
+    let tables = if tables.is_empty() { vec![single] } else { tables };
+    let described = match input {
+        Some(input) if tables.len() > 1 => {
+            let urls: Vec<_> = tables.iter().filter_map(|table| table.url.as_deref()).collect();
+            let is_input = |table: &Table| table.url.as_deref().is_some_and(|url| same_file(&dir.join(url), Path::new(input)));
+            tables.iter().position(is_input).ok_or_else(|| invalid(&format!("no table for {}, it has: {}", input, urls.join(", "))))?
+        }
+        None if tables.len() > 1 => return Err(invalid(&"it describes several tables, give the input file")),
+        _ => 0,
+    };
+
+    let mut schemas = Vec::with_capacity(tables.len());
+    let mut headers = Vec::with_capacity(tables.len());
+    for table in &tables {
+        let schema = match &table.table_schema {
+            Some(SchemaOrPath::Path(schema_path)) => fs::read_to_string(dir.join(schema_path))
+                .map_err(|e| e.to_string())
+                .and_then(|source| serde_json::from_str(&source).map_err(|e| e.to_string()))
+                .map_err(|e| invalid(&format!("table schema {}: {}", schema_path, e)))?,
+            _ => Schema::default(),
+        };
+        schemas.push(schema);
+        // what the header of each table holds for each column, for the foreign keys referencing it
+        let columns = match &table.table_schema {
+            Some(SchemaOrPath::Schema(schema)) => &schema.columns,
+            _ => &schemas[schemas.len() - 1].columns,
+        };
+        let titles: BTreeMap<_, _> = columns.iter().enumerate().map(|(i, column)| (column_name(column, i), column_title(column, i))).collect();
+        headers.push((table.url.as_deref().map(|url| dir.join(url)), titles));
+    }
+    let table = tables.into_iter().nth(described).unwrap_or_default();
+    let schema = match table.table_schema {
+        Some(SchemaOrPath::Schema(schema)) => schema,
+        _ => schemas.swap_remove(described),
+    };
+
+    let mut warnings = Vec::new();
+    let common = common_settings(table.dialect.or(group_dialect).unwrap_or_default(), &mut warnings);
+    let inherited = schema.inherited.or(&table.inherited.or(&group_inherited));
+
+    let mut columns = Vec::new();
+    for (i, column) in schema.columns.iter().enumerate().filter(|(_, column)| !column.is_virtual) {
+        let properties = column.inherited.or(&inherited);
+        let mut defined = Column {
+            titles: column.titles.as_ref().map(Titles::to_vec).unwrap_or_default(),
+            required: properties.required.unwrap_or_default(),
+            missing_values: properties.null.map(Names::into_vec),
+            ..Column::new(column_name(column, i), ColumnType::String)
+        };
+        if column.separator.is_some() {
+            warnings.push(format!("column '{}': lists of values aren't supported, its values are read as strings", defined.name));
+        } else if let Some(datatype) = &properties.datatype {
+            warnings.extend(apply_datatype(&mut defined, datatype));
+        }
+        columns.push(defined);
+    }
+    anyhow::ensure!(!columns.is_empty(), "invalid CSVW metadata {}: no columns for the table", path.display());
+
+    let mut unique_keys = Vec::new();
+    if let Some(primary_key) = schema.primary_key {
+        let primary_key = primary_key.into_vec();
+        for column in columns.iter_mut().filter(|column| primary_key.contains(&column.name)) {
+            column.required = true;
+        }
+        unique_keys.push(primary_key);
+    }
+
+    let mut foreign_keys = Vec::new();
+    for key in schema.foreign_keys {
+        let Some(resource) = key.reference.resource else {
+            let reference = key.reference.schema_reference.unwrap_or_default();
+            warnings.push(format!("foreign keys to a schema ({}) aren't supported, the key isn't checked", reference));
+            continue;
+        };
+        let referenced = dir.join(&resource);
+        let titles = headers.iter().find(|(url, _)| url.as_ref().is_some_and(|url| same_file(url, &referenced)));
+        let reference_columns = key
+            .reference
+            .column_reference
+            .into_vec()
+            .into_iter()
+            .map(|name| titles.and_then(|(_, titles)| titles.get(&name)).cloned().unwrap_or(name))
+            .collect();
+        let reference = match input {
+            Some(input) if same_file(&referenced, Path::new(input)) => input.to_string(),
+            _ => referenced.display().to_string(),
+        };
+        foreign_keys.push(ForeignKey { columns: key.column_reference.into_vec(), reference, reference_columns });
+    }
+
+    let field_count = ValidatorSpec::new_field_count(columns.len());
+    let columns = ValidatorSpec::Columns {
+        id: None,
+        columns,
+        missing_values: vec![String::new()],
+        unique_keys,
+        foreign_keys,
+        enabled: true,
+        severity: Severity::Error,
+        common: None,
+    };
+    Ok(Metadata { common, validators: vec![field_count, columns], warnings })
+}
+
+/// The `name` of the column, or else its first title, or else `_col.<n>`.
+fn column_name(column: &SchemaColumn, index: usize) -> String {
+    column.name.clone().or_else(|| column_title_of(column)).unwrap_or_else(|| format!("_col.{}", index + 1))
+}
+
+/// What the header holds for the column: its first title, or else its name.
+fn column_title(column: &SchemaColumn, index: usize) -> String {
+    column_title_of(column).unwrap_or_else(|| column_name(column, index))
+}
+
+fn column_title_of(column: &SchemaColumn) -> Option<String> {
+    column.titles.as_ref().and_then(|titles| titles.to_vec().into_iter().next())
+}
+
+/// Both paths are the same file, or the same path when they don't exist.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// The CSVW dialect as common settings, its defaults included: the metadata describes the file completely.
+fn common_settings(dialect: Dialect, warnings: &mut Vec<String>) -> CommonSettings {
+    for (option, default) in unsupported_options() {
+        if let Some(value) = dialect.options.get(option).filter(|value| **value != default) {
+            warnings.push(format!("dialect {} {} isn't supported, it's ignored", option, value));
+        }
+    }
+    // the reader ends lines at `\n`, and the validators leave out the `\r` of a `\r\n`
+    if let Some(value) = &dialect.line_terminators {
+        let terminators: Option<Vec<&str>> = match value {
+            Value::String(terminator) => Some(vec![terminator]),
+            Value::Array(terminators) => terminators.iter().map(Value::as_str).collect(),
+            _ => None,
+        };
+        if !terminators.is_some_and(|terminators| !terminators.is_empty() && terminators.iter().all(|t| matches!(*t, "\n" | "\r\n"))) {
+            warnings.push(format!("dialect lineTerminators {} isn't supported, lines end at \\n or \\r\\n", value));
+        }
+    }
+    let quote_char = match dialect.quote_char {
+        None => '"',
+        Some(Value::String(quote)) if quote.chars().count() == 1 => quote.chars().next().unwrap_or('"'),
+        Some(other) => {
+            warnings.push(format!("dialect quoteChar {} isn't supported, fields are read with \" quotes", other));
+            '"'
+        }
+    };
+    let header_rows = dialect.header_row_count.unwrap_or(usize::from(dialect.header.unwrap_or(true)));
+    CommonSettings {
+        separator: Some(dialect.delimiter.unwrap_or(',')),
+        quote_char: Some(quote_char),
+        has_header: Some(header_rows > 0),
+        // the header rows before the last one are skipped
+        skip_rows: Some(dialect.skip_rows.unwrap_or_default() + header_rows.saturating_sub(1)),
+    }
+}
+
+/// Sets the type and constraints of the column from its datatype, returns a warning about what isn't checked.
+fn apply_datatype(column: &mut Column, datatype: &Datatype) -> Option<String> {
+    let (base, description) = match datatype {
+        Datatype::Base(base) => (base.as_str(), None),
+        Datatype::Described(description) => (description.base.as_str(), Some(description.as_ref())),
+    };
+    let base = base.trim_start_matches("http://www.w3.org/2001/XMLSchema#").trim_start_matches("xsd:");
+    let read_as_string = |what: String| Some(format!("column '{}': {}, its values are read as strings", column.name, what));
+
+    // the integer types imply their bounds
+    let (datatype, implied) = match base {
+        "string" | "normalizedString" | "token" | "language" | "Name" | "NCName" | "NMTOKEN" | "anyURI" | "any" => {
+            (ColumnType::String, None)
+        }
+        "integer" => (ColumnType::Integer, None),
+        "long" => (ColumnType::Integer, Some((i64::MIN.to_string(), i64::MAX.to_string()))),
+        "int" => (ColumnType::Integer, Some((i32::MIN.to_string(), i32::MAX.to_string()))),
+        "short" => (ColumnType::Integer, Some((i16::MIN.to_string(), i16::MAX.to_string()))),
+        "byte" => (ColumnType::Integer, Some((i8::MIN.to_string(), i8::MAX.to_string()))),
+        "unsignedLong" => (ColumnType::Integer, Some(("0".to_string(), u64::MAX.to_string()))),
+        "unsignedInt" => (ColumnType::Integer, Some(("0".to_string(), u32::MAX.to_string()))),
+        "unsignedShort" => (ColumnType::Integer, Some(("0".to_string(), u16::MAX.to_string()))),
+        "unsignedByte" => (ColumnType::Integer, Some(("0".to_string(), u8::MAX.to_string()))),
+        "nonNegativeInteger" | "positiveInteger" | "nonPositiveInteger" | "negativeInteger" => (ColumnType::Integer, None),
+        "decimal" | "double" | "float" | "number" => (ColumnType::Number, None),
+        "boolean" => (ColumnType::Boolean, None),
+        "date" => (ColumnType::Date, None),
+        "time" => (ColumnType::Time, None),
+        "dateTime" | "dateTimeStamp" | "datetime" => (ColumnType::Datetime, None),
+        "gYear" => (ColumnType::Year, None),
+        "gYearMonth" => (ColumnType::Yearmonth, None),
+        other => return read_as_string(format!("datatype '{}' isn't supported", other)),
+    };
+    column.datatype = datatype;
+    if let Some((minimum, maximum)) = implied {
+        column.minimum = Some(minimum);
+        column.maximum = Some(maximum);
+    }
+    match base {
+        "nonNegativeInteger" => column.minimum = Some("0".to_string()),
+        "positiveInteger" => column.minimum = Some("1".to_string()),
+        "nonPositiveInteger" => column.maximum = Some("0".to_string()),
+        "negativeInteger" => column.maximum = Some("-1".to_string()),
+        _ => {}
+    }
+    let description = description?;
+
+    column.min_length = description.min_length.or(description.length);
+    column.max_length = description.max_length.or(description.length);
+    let mut warning = None;
+    if let Some(format) = &description.format {
+        match (datatype, format) {
+            // the format of a string is a regular expression
+            (ColumnType::String, Value::String(pattern)) => column.pattern = Some(pattern.clone()),
+            // the format of a boolean is `true|false`
+            (ColumnType::Boolean, Value::String(values)) if values.split('|').count() == 2 => {
+                column.datatype = ColumnType::String;
+                column.allowed = Some(values.split('|').map(str::to_string).collect());
+                return None;
+            }
+            _ => {
+                column.datatype = ColumnType::String;
+                column.minimum = None;
+                column.maximum = None;
+                return read_as_string(format!("format {} isn't supported", format));
+            }
+        }
+    }
+    if datatype != ColumnType::String {
+        column.minimum = description.minimum.as_ref().map(text).or(column.minimum.take());
+        column.maximum = description.maximum.as_ref().map(text).or(column.maximum.take());
+        // exclusive bounds of integers are the inclusive ones next to them
+        let next = |value: &Value, step: i128| text(value).parse::<i128>().ok().map(|value| (value + step).to_string());
+        match (datatype, &description.min_exclusive, &description.max_exclusive) {
+            (_, None, None) => {}
+            (ColumnType::Integer, min, max) => {
+                column.minimum = min.as_ref().and_then(|min| next(min, 1)).or(column.minimum.take());
+                column.maximum = max.as_ref().and_then(|max| next(max, -1)).or(column.maximum.take());
+            }
+            _ => warning = Some(format!("column '{}': exclusive bounds aren't supported, they're not checked", column.name)),
+        }
+    }
+    warning
+}
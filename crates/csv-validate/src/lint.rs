@@ -0,0 +1,708 @@
+use csv_validator_core::{Column, ColumnType, ColumnsValidator, CommonSettings, Dialect, FieldCountRepair, MergeColumn, Severity, ValidatorSpec};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use crate::config::ConfigFile;
+
+/// A problem in a config file, at a line and column counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.line, self.column, self.severity.as_str(), self.message)
+    }
+}
+
+/// All the problems of a config file at once, where loading it stops at the first one:
+/// unknown keys, values of the wrong type, settings that can't work, and settings that are never used (warnings).
+pub fn check_config(source: &str) -> Vec<Finding> {
+    let mut linter = Linter::default();
+    let mut tree = TreeBuilder::default();
+    match Parser::new_from_str(source).load(&mut tree, false) {
+        Err(e) => linter.error(Position::from(e.marker()), e.info()),
+        Ok(()) => match tree.root {
+            Some(root) => linter.check_file(&root, source),
+            None => linter.error(Position { line: 1, column: 1 }, "the config is empty, expected `common` and `validators`"),
+        },
+    }
+    linter.findings.sort_by_key(|finding| (finding.line, finding.column));
+    linter.findings
+}
+
+/// The config's JSON Schema, generated from its types: the keys the linter knows are read from it.
+static SCHEMA: LazyLock<Value> = LazyLock::new(|| schemars::schema_for!(ConfigFile).to_value());
+static KEYS: LazyLock<Keys> = LazyLock::new(Keys::from_schema);
+
+/// The keys of each mapping of the config.
+struct Keys {
+    file: Vec<&'static str>,
+    profile: Vec<&'static str>,
+    common: Vec<&'static str>,
+    column: Vec<&'static str>,
+    foreign_key: Vec<&'static str>,
+    foreign_key_required: Vec<&'static str>,
+    validators: Vec<ValidatorKeys>,
+}
+
+/// The keys of a validator type, `type` and the shared settings included.
+struct ValidatorKeys {
+    name: &'static str,
+    keys: Vec<&'static str>,
+    /// the setting the type can't do without
+    required: &'static str,
+}
+
+impl Keys {
+    fn from_schema() -> Self {
+        let schema: &'static Value = &SCHEMA;
+        let definition = |name: &str| &schema["$defs"][name];
+        let variants = definition("ValidatorSpec")["oneOf"].as_array().into_iter().flatten();
+        Keys {
+            file: properties(schema),
+            profile: properties(definition("Profile")),
+            common: properties(definition("CommonSettings")),
+            column: properties(definition("Column")),
+            foreign_key: properties(definition("ForeignKey")),
+            foreign_key_required: required(definition("ForeignKey")),
+            validators: variants
+                .map(|variant| ValidatorKeys {
+                    name: variant["properties"]["type"]["const"].as_str().unwrap_or_default(),
+                    keys: properties(variant),
+                    required: required(variant).into_iter().find(|key| *key != "type").unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+
+    fn validator_types(&self) -> impl Iterator<Item = &'static str> + Clone + '_ {
+        self.validators.iter().map(|validator| validator.name)
+    }
+}
+
+fn properties(schema: &'static Value) -> Vec<&'static str> {
+    schema["properties"].as_object().into_iter().flat_map(|properties| properties.keys()).map(String::as_str).collect()
+}
+
+fn required(schema: &'static Value) -> Vec<&'static str> {
+    schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str).collect()
+}
+
+/// The `key: value` pairs of a mapping, in the order of the file.
+type Entries<'a> = Vec<(&'a str, &'a Node, &'a Node)>;
+
+fn get<'a>(entries: &Entries<'a>, name: &str) -> Option<(&'a Node, &'a Node)> {
+    entries.iter().find(|(key, _, _)| *key == name).map(|(_, key, value)| (*key, *value))
+}
+
+#[derive(Default)]
+struct Linter {
+    findings: Vec<Finding>,
+}
+
+impl Linter {
+    fn report(&mut self, at: Position, severity: Severity, message: impl Into<String>) {
+        self.findings.push(Finding { line: at.line, column: at.column, severity, message: message.into() });
+    }
+
+    fn error(&mut self, at: Position, message: impl Into<String>) {
+        self.report(at, Severity::Error, message);
+    }
+
+    fn warning(&mut self, at: Position, message: impl Into<String>) {
+        self.report(at, Severity::Warning, message);
+    }
+
+    fn check_file(&mut self, root: &Node, source: &str) {
+        let Some(entries) = self.entries(root, "the config", &KEYS.file) else { return };
+        // an included file can hold any part of the config, the other parts are only checked on the resolved config
+        let include = get(&entries, "include");
+        if let Some((_, value)) = include {
+            self.strings(value, "`include`");
+        }
+        let common = get(&entries, "common");
+        if let Some((_, value)) = common {
+            self.check_common(value);
+        }
+        let (items, checked): (_, Vec<_>) = match get(&entries, "validators") {
+            Some((_, validators)) => {
+                let items = self.sequence(validators, "`validators`");
+                (items, items.iter().map(|item| self.check_validator(item)).collect())
+            }
+            None => (&[][..], Vec::new()),
+        };
+        let ids: Vec<_> = checked.iter().filter_map(|checked| checked.id.as_ref()).collect();
+        for (i, (id, at)) in ids.iter().enumerate() {
+            if ids[..i].iter().any(|(earlier, _)| earlier == id) {
+                self.error(*at, format!("duplicate validator id '{}'", id));
+            }
+        }
+        if let Some((_, value)) = get(&entries, "profiles") {
+            self.check_profiles(value, &ids);
+        }
+        if items.is_empty() && include.is_none() {
+            self.warning(root.at, "no validators: only the validator flags will run");
+        }
+        if let (Some((_, value)), None) = (common, include) {
+            self.check_unused_common(value, &checked);
+        }
+
+        // what can only be checked on the settings together, once they are all well-formed
+        if self.findings.iter().any(|f| f.severity == Severity::Error) {
+            return;
+        }
+        let config: ConfigFile = match serde_yaml::from_str(source) {
+            Ok(config) => config,
+            Err(e) => {
+                let at = e.location().map(|l| Position { line: l.line(), column: l.column() });
+                self.error(at.unwrap_or(root.at), e.to_string());
+                return;
+            }
+        };
+        for (spec, item) in config.validators.iter().zip(items) {
+            if let Err(e) = without_references(spec.clone()).into_validator(Dialect::default().separator) {
+                self.error(item.at, e.to_string());
+            }
+        }
+        if let (Some((validators_key, _)), None) = (get(&entries, "validators"), include) {
+            if let Err(e) = Dialect::resolve(&CommonSettings::default(), &config.common.unwrap_or_default(), &config.validators) {
+                self.error(validators_key.at, e.to_string());
+            }
+        }
+    }
+
+    /// Profiles enable and disable validators by type or id. An id of another file is only checked on the
+    /// resolved config, here it's only an error when it looks like a misspelled type.
+    fn check_profiles(&mut self, node: &Node, ids: &[&(String, Position)]) {
+        let NodeKind::Mapping(profiles) = &node.kind else {
+            self.error(node.at, format!("expected a mapping of profile names for `profiles`, got {}", node.describe()));
+            return;
+        };
+        let types = KEYS.validator_types();
+        for (name, profile) in profiles {
+            let what = format!("profile {}", name.describe());
+            for (_, _, list) in self.entries(profile, &what, &KEYS.profile).unwrap_or_default() {
+                for (picked, at) in self.strings(list, &what) {
+                    if types.clone().any(|known| known == picked) || ids.iter().any(|(id, _)| *id == picked) {
+                        continue;
+                    }
+                    if let Some(kind) = suggestion(&picked, types.clone()) {
+                        let expected = types.clone().collect::<Vec<_>>().join(", ");
+                        let message = format!("unknown validator type `{}` in {}, expected one of: {}", picked, what, expected);
+                        self.error(at, format!("{} (did you mean `{}`?)", message, kind));
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_common(&mut self, node: &Node) -> CommonSettings {
+        let mut settings = CommonSettings::default();
+        for (name, key, value) in self.entries(node, "`common`", &KEYS.common).unwrap_or_default() {
+            match name {
+                "separator" => settings.separator = self.char(name, value),
+                "quote_char" => settings.quote_char = self.char(name, value),
+                "skip_rows" => settings.skip_rows = self.whole_number(name, value),
+                "has_header" => settings.has_header = self.flag(name, value),
+                _ => self.unchecked(key, "`common`"),
+            }
+        }
+        settings
+    }
+
+    fn check_validator(&mut self, node: &Node) -> Checked {
+        let mut checked = Checked { id: None, enabled: true, common: None };
+        let type_names = KEYS.validator_types();
+        let Some(kind) = node.get("type") else {
+            let expected = type_names.collect::<Vec<_>>().join(", ");
+            self.error(node.at, format!("expected a validator with a `type`, one of: {}", expected));
+            return checked;
+        };
+        let Some(validator) = KEYS.validators.iter().find(|validator| kind.text() == Some(validator.name)) else {
+            let expected = type_names.clone().collect::<Vec<_>>().join(", ");
+            let message = format!("unknown validator type {}, expected one of: {}", kind.describe(), expected);
+            self.error(kind.at, with_suggestion(message, kind.text(), type_names));
+            return checked;
+        };
+
+        let what = format!("the {} validator", validator.name);
+        let Some(entries) = self.entries(node, &what, &validator.keys) else { return checked };
+        if get(&entries, validator.required).is_none() {
+            self.error(node.at, format!("{} needs `{}`", what, validator.required));
+        }
+
+        let mut patterns = Vec::new();
+        let mut replacements = None;
+        let mut count = None;
+        let mut repair = None;
+        let mut columns = Vec::new();
+        let mut keys = Vec::new();
+        for &(name, key, value) in &entries {
+            match name {
+                "id" => match value.text().filter(|_| !value.is_null()) {
+                    Some(id) => checked.id = Some((id.to_string(), value.at)),
+                    None => self.error(value.at, format!("`id` must be a string, got {}", value.describe())),
+                },
+                "enabled" => checked.enabled = self.flag(name, value).unwrap_or(true),
+                "severity" => {
+                    self.parsed::<Severity>(value);
+                }
+                "common" => checked.common = Some(self.check_common(value)),
+                "illegal_chars" => patterns = self.patterns(key, value),
+                "replace_with" => replacements = Some((key, self.strings(value, "`replace_with`"))),
+                "fix" => {
+                    self.flag(name, value);
+                    self.warning(key.at, "`fix` is deprecated and ignored: fix mode is on with --fix-output, and uses `replace_with`");
+                }
+                "expected" | "max_length" => count = self.count(name, value),
+                "repair" => repair = self.parsed::<FieldCountRepair>(value).map(|repair| (value, repair)),
+                "columns" => columns = self.check_columns(value),
+                "missing_values" => {
+                    self.strings(value, "`missing_values`");
+                }
+                "unique_keys" => keys.extend(self.sequence(value, "`unique_keys`").iter().map(|key| (key, "`unique_keys`"))),
+                "foreign_keys" => {
+                    for foreign in self.sequence(value, "`foreign_keys`") {
+                        keys.extend(self.check_foreign_key(foreign).map(|key| (key, "a foreign key")));
+                    }
+                }
+                // checked first, it picks the keys
+                "type" => {}
+                _ => self.unchecked(key, &what),
+            }
+        }
+
+        // settings that depend on each other
+        if let Some((key, replacements)) = replacements.filter(|(_, r)| !patterns.is_empty() && r.len() != patterns.len()) {
+            self.error(
+                key.at,
+                format!(
+                    "`replace_with` has {} entries and `illegal_chars` {}: each pattern is replaced by the entry at its position",
+                    replacements.len(),
+                    patterns.len()
+                ),
+            );
+        }
+        if let (Some((value, FieldCountRepair::Merge(MergeColumn::Index(column)))), Some(expected)) = (repair, count) {
+            if column >= expected {
+                self.error(
+                    value.at,
+                    format!("merge column {} is out of range, with {} fields expected the last one is {}", column, expected, expected - 1),
+                );
+            }
+        }
+        if !columns.is_empty() {
+            for (key, what) in keys {
+                self.check_key(key, what, &columns);
+            }
+        }
+        checked
+    }
+
+    /// The columns of the columns validator, returns their names.
+    fn check_columns(&mut self, node: &Node) -> Vec<String> {
+        let items = self.sequence(node, "`columns`");
+        if matches!(&node.kind, NodeKind::Sequence(items) if items.is_empty()) {
+            self.error(node.at, "`columns` is empty, expected at least one column");
+        }
+        let mut names: Vec<String> = Vec::new();
+        for item in items {
+            let Some(entries) = self.entries(item, "a column", &KEYS.column) else { continue };
+            let mut column = Column::default();
+            let mut valid = true;
+            for &(name, key, value) in &entries {
+                let mut valid_if = |value: Option<()>| valid &= value.is_some();
+                match name {
+                    "name" => valid_if(value.text().filter(|_| !value.is_null()).map(|text| column.name = text.to_string())),
+                    "titles" => {
+                        self.strings(value, "`titles`");
+                    }
+                    "missing_values" => {
+                        self.strings(value, "`missing_values`");
+                    }
+                    "type" => valid_if(self.parsed::<ColumnType>(value).map(|datatype| column.datatype = datatype)),
+                    "required" => valid_if(self.flag(name, value).map(|required| column.required = required)),
+                    "unique" => valid_if(self.flag(name, value).map(|unique| column.unique = unique)),
+                    "enum" => column.allowed = Some(self.strings(value, "`enum`").into_iter().map(|(value, _)| value).collect()),
+                    "pattern" => column.pattern = value.text().map(str::to_string),
+                    "minimum" => column.minimum = value.text().map(str::to_string),
+                    "maximum" => column.maximum = value.text().map(str::to_string),
+                    "min_length" => column.min_length = self.whole_number(name, value),
+                    "max_length" => column.max_length = self.whole_number(name, value),
+                    _ => self.unchecked(key, "a column"),
+                }
+            }
+            if get(&entries, "name").is_none_or(|(_, value)| value.is_null()) {
+                self.error(item.at, "a column needs a `name`");
+                continue;
+            }
+            if names.contains(&column.name) {
+                self.error(item.at, format!("duplicate column '{}'", column.name));
+            }
+            names.push(column.name.clone());
+            // invalid patterns, and minimums or maximums that aren't values of the column
+            if let Some(Err(e)) = valid.then(|| ColumnsValidator::new(vec![column], b',')) {
+                self.error(item.at, e.to_string());
+            }
+        }
+        names
+    }
+
+    /// A foreign key, returns its `columns`, which are checked against the columns of the validator.
+    fn check_foreign_key<'a>(&mut self, node: &'a Node) -> Option<&'a Node> {
+        let entries = self.entries(node, "a foreign key", &KEYS.foreign_key)?;
+        for name in &KEYS.foreign_key_required {
+            if get(&entries, name).is_none() {
+                self.error(node.at, format!("a foreign key needs `{}`", name));
+            }
+        }
+        let lengths: Vec<_> = ["columns", "reference_columns"]
+            .iter()
+            .filter_map(|name| get(&entries, name))
+            .map(|(_, value)| self.strings(value, "a foreign key").len())
+            .collect();
+        if let [columns, reference_columns] = lengths[..] {
+            if columns != reference_columns {
+                let message = format!("a foreign key has {} columns and {} reference_columns, expected one each", columns, reference_columns);
+                self.error(node.at, message);
+            }
+        }
+        get(&entries, "columns").map(|(_, value)| value)
+    }
+
+    /// A unique or foreign key: a list of one or more of the `columns`.
+    fn check_key(&mut self, key: &Node, what: &str, columns: &[String]) {
+        let names = self.strings(key, what);
+        if names.is_empty() && matches!(&key.kind, NodeKind::Sequence(_)) {
+            self.error(key.at, format!("empty key in {}, expected at least one column", what));
+        }
+        for (name, at) in names {
+            if !columns.contains(&name) {
+                let message = format!("no column '{}' for the key in {}", name, what);
+                self.error(at, with_suggestion(message, Some(&name), columns.iter().map(String::as_str)));
+            }
+        }
+    }
+
+    /// Warns about the top-level common settings that every enabled validator overrides.
+    fn check_unused_common(&mut self, common: &Node, validators: &[Checked]) {
+        let enabled: Vec<_> = validators.iter().filter(|checked| checked.enabled).collect();
+        let NodeKind::Mapping(pairs) = &common.kind else { return };
+        if enabled.is_empty() {
+            return;
+        }
+        for (key, _) in pairs {
+            let overridden = enabled.iter().all(|checked| match (&checked.common, key.text()) {
+                (Some(own), Some("separator")) => own.separator.is_some(),
+                (Some(own), Some("quote_char")) => own.quote_char.is_some(),
+                (Some(own), Some("has_header")) => own.has_header.is_some(),
+                (Some(own), Some("skip_rows")) => own.skip_rows.is_some(),
+                _ => false,
+            });
+            if overridden {
+                self.warning(key.at, format!("{} is never used, every enabled validator sets its own", key.describe()));
+            }
+        }
+    }
+
+    /// A key of the schema the linter has no check for yet: it's only checked once the config loads.
+    fn unchecked(&mut self, key: &Node, what: &str) {
+        self.warning(key.at, format!("{} in {} isn't checked by check-config, only when the config loads", key.describe(), what));
+    }
+
+    /// The entries of a mapping, reporting the keys that are unknown or duplicated.
+    fn entries<'a>(&mut self, node: &'a Node, what: &str, allowed: &[&str]) -> Option<Entries<'a>> {
+        let NodeKind::Mapping(pairs) = &node.kind else {
+            self.error(node.at, format!("expected a mapping for {}, got {}", what, node.describe()));
+            return None;
+        };
+        let mut entries: Entries = Vec::new();
+        for (key, value) in pairs {
+            let Some(name) = key.text().filter(|name| allowed.contains(name)) else {
+                let message = format!("unknown key {} in {}, expected one of: {}", key.describe(), what, allowed.join(", "));
+                self.error(key.at, with_suggestion(message, key.text(), allowed.iter().copied()));
+                continue;
+            };
+            if get(&entries, name).is_some() {
+                self.error(key.at, format!("duplicate key `{}` in {}", name, what));
+                continue;
+            }
+            entries.push((name, key, value));
+        }
+        Some(entries)
+    }
+
+    fn sequence<'a>(&mut self, node: &'a Node, what: &str) -> &'a [Node] {
+        match &node.kind {
+            NodeKind::Sequence(items) => items,
+            _ => {
+                self.error(node.at, format!("expected a list for {}, got {}", what, node.describe()));
+                &[]
+            }
+        }
+    }
+
+    fn strings(&mut self, node: &Node, what: &str) -> Vec<(String, Position)> {
+        let mut strings = Vec::new();
+        for item in self.sequence(node, what) {
+            match item.text() {
+                Some(value) if !item.is_null() => strings.push((value.to_string(), item.at)),
+                _ => self.error(item.at, format!("expected a string, got {}", item.describe())),
+            }
+        }
+        strings
+    }
+
+    /// The `illegal_chars` patterns: at least one, none of them empty or listed twice.
+    fn patterns(&mut self, key: &Node, node: &Node) -> Vec<(String, Position)> {
+        let patterns = self.strings(node, "`illegal_chars`");
+        if patterns.is_empty() && matches!(&node.kind, NodeKind::Sequence(items) if items.is_empty()) {
+            self.error(key.at, "`illegal_chars` is empty, the validator would never find anything");
+        }
+        for (i, (pattern, at)) in patterns.iter().enumerate() {
+            if pattern.is_empty() {
+                self.error(*at, "empty pattern, it would match everywhere");
+            } else if patterns[..i].iter().any(|(earlier, _)| earlier == pattern) {
+                self.warning(*at, format!("'{}' is listed twice, the first one always matches: this one is never used", pattern));
+            }
+        }
+        patterns
+    }
+
+    fn count(&mut self, name: &str, node: &Node) -> Option<usize> {
+        match self.whole_number(name, node) {
+            Some(0) => {
+                self.error(node.at, format!("`{}` must be at least 1", name));
+                None
+            }
+            count => count,
+        }
+    }
+
+    fn whole_number(&mut self, name: &str, node: &Node) -> Option<usize> {
+        let number = node.plain().and_then(|plain| plain.parse().ok());
+        if number.is_none() {
+            self.error(node.at, format!("`{}` must be a whole number, got {}", name, node.describe()));
+        }
+        number
+    }
+
+    fn flag(&mut self, name: &str, node: &Node) -> Option<bool> {
+        match node.plain() {
+            Some("true" | "True" | "TRUE") => Some(true),
+            Some("false" | "False" | "FALSE") => Some(false),
+            _ => {
+                self.error(node.at, format!("`{}` must be true or false, got {}", name, node.describe()));
+                None
+            }
+        }
+    }
+
+    /// A separator or quote: one ASCII character.
+    fn char(&mut self, name: &str, node: &Node) -> Option<char> {
+        let mut chars = node.text().filter(|_| !node.is_null()).unwrap_or_default().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii() => Some(c),
+            (Some(c), None) => {
+                self.error(node.at, format!("`{}` must be an ASCII character, got {:?}", name, c));
+                None
+            }
+            _ => {
+                self.error(node.at, format!("`{}` must be a single character, got {}", name, node.describe()));
+                None
+            }
+        }
+    }
+
+    /// A setting parsed from its string form, ie. `severity` or `repair`.
+    fn parsed<T: FromStr<Err = String>>(&mut self, node: &Node) -> Option<T> {
+        match node.text().filter(|_| !node.is_null()).map(str::parse) {
+            Some(Ok(value)) => Some(value),
+            Some(Err(message)) => {
+                self.error(node.at, message);
+                None
+            }
+            None => {
+                self.error(node.at, format!("expected a string, got {}", node.describe()));
+                None
+            }
+        }
+    }
+}
+
+/// The spec without its foreign keys, to check its settings without reading the files they reference:
+/// their columns are checked against the validator's, the files are only read when it runs.
+pub(crate) fn without_references(mut spec: ValidatorSpec) -> ValidatorSpec {
+    if let ValidatorSpec::Columns { foreign_keys, .. } = &mut spec {
+        foreign_keys.clear();
+    }
+    spec
+}
+
+/// What the checks across validators need to know about one.
+struct Checked {
+    id: Option<(String, Position)>,
+    enabled: bool,
+    common: Option<CommonSettings>,
+}
+
+/// Adds a "did you mean" when `found` looks like a typo of one of the `known` names.
+fn with_suggestion<'a>(message: String, found: Option<&str>, known: impl Iterator<Item = &'a str>) -> String {
+    match found.and_then(|found| suggestion(found, known)) {
+        Some(name) => format!("{} (did you mean `{}`?)", message, name),
+        None => message,
+    }
+}
+
+/// The known name `found` looks like a typo of, if any.
+fn suggestion<'a>(found: &str, known: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    known.map(|name| (edit_distance(found, name), name)).filter(|(distance, _)| *distance <= 2).min().map(|(_, name)| name)
+}
+
+/// Levenshtein distance, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            current.push((previous[j] + usize::from(ca != *cb)).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Line and column, both counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl From<&Marker> for Position {
+    fn from(marker: &Marker) -> Self {
+        // yaml-rust2 counts columns from 0
+        Position { line: marker.line(), column: marker.col() + 1 }
+    }
+}
+
+/// A YAML node and where it starts, which serde_yaml doesn't tell.
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    at: Position,
+}
+
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Scalar { value: String, plain: bool },
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+impl Node {
+    fn text(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// An unquoted scalar, which YAML reads as a number, a boolean or null when it looks like one.
+    fn plain(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Scalar { value, plain: true } => Some(value),
+            _ => None,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self.plain(), Some("" | "~" | "null" | "Null" | "NULL"))
+    }
+
+    fn get(&self, name: &str) -> Option<&Node> {
+        match &self.kind {
+            NodeKind::Mapping(pairs) => pairs.iter().find(|(key, _)| key.text() == Some(name)).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.kind {
+            _ if self.is_null() => "nothing".to_string(),
+            NodeKind::Scalar { value, .. } => format!("`{}`", value),
+            NodeKind::Sequence(_) => "a list".to_string(),
+            NodeKind::Mapping(_) => "a mapping".to_string(),
+        }
+    }
+}
+
+/// Builds the nodes of the first document from the parser events, resolving aliases.
+#[derive(Default)]
+struct TreeBuilder {
+    root: Option<Node>,
+    /// the open sequences and mappings, with their anchor and, in a mapping, the key waiting for its value
+    open: Vec<(Node, usize, Option<Node>)>,
+    anchors: HashMap<usize, Node>,
+}
+
+impl TreeBuilder {
+    fn add(&mut self, node: Node, anchor: usize) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        let Some((parent, _, key)) = self.open.last_mut() else {
+            self.root.get_or_insert(node);
+            return;
+        };
+        match &mut parent.kind {
+            NodeKind::Sequence(items) => items.push(node),
+            NodeKind::Mapping(pairs) => match key.take() {
+                Some(key) => pairs.push((key, node)),
+                None => {
+                    // the parser marks a block mapping after its first key, point at that key instead
+                    if pairs.is_empty() {
+                        parent.at = node.at;
+                    }
+                    *key = Some(node);
+                }
+            },
+            NodeKind::Scalar { .. } => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for TreeBuilder {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        let at = Position::from(&marker);
+        match event {
+            Event::Scalar(value, style, anchor, _) => {
+                self.add(Node { kind: NodeKind::Scalar { value, plain: style == TScalarStyle::Plain }, at }, anchor)
+            }
+            Event::SequenceStart(anchor, _) => self.open.push((Node { kind: NodeKind::Sequence(Vec::new()), at }, anchor, None)),
+            Event::MappingStart(anchor, _) => self.open.push((Node { kind: NodeKind::Mapping(Vec::new()), at }, anchor, None)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some((node, anchor, _)) = self.open.pop() {
+                    self.add(node, anchor);
+                }
+            }
+            Event::Alias(anchor) => {
+                if let Some(node) = self.anchors.get(&anchor).cloned() {
+                    self.add(node, 0);
+                }
+            }
+            _ => {}
+        }
+    }
+}
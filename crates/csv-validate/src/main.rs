@@ -158,45 +158,102 @@
 //
 
 
-use clap::{Parser, Args};
-use std::fs::File;
-use std::io::{self, Read, Write};
+use clap::{Parser, Args, Subcommand};
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
-use crossbeam_channel::unbounded;
-use rayon::ThreadPoolBuilder;
+use std::time::Duration;
 
 use csv_validator_core::{
-    Validator,
-    IllegalCharactersValidator,
-    FieldCountValidator,
-    LineLengthValidator,
-    OptimizedQuoteAwareReader,
-    execute_validators,
+    CommonSettings,
+    Dialect,
+    FieldCountRepair,
+    IssueStatus,
+    ProgressCallback,
+    Severity,
     ValidationIssue,
+    ValidationOptions,
+    ValidatorSpec,
+    into_validators,
+    validate_file_with_sink,
+    validate_reader_with_sink,
 };
 
 pub mod config;
+mod csvw;
+mod lint;
+mod progress;
+mod summary;
+mod table_schema;
+
+use progress::ProgressBar;
 
 
 #[derive(Parser, Debug)]
-#[command(version = "1.0", about = "High-performance CSV validator")]
+#[command(version = "1.0", about = "High-performance CSV validator", args_conflicts_with_subcommands = true)]
 struct CliArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Optional input file (defaults to stdin if not provided)
     #[arg(value_name = "FILE")]
     input: Option<String>,
 
-    /// Use YAML config file instead of CLI flags
+    /// YAML config file: validators and common settings. Validator flags add to its validators,
+    /// and replace those of the same type; --separator, --quote-char and --has-header override its common settings
     #[arg(long, value_name = "YAML")]
     config: Option<String>,
 
-    /// CSV delimiter
-    #[arg(long, default_value = ",")]
-    separator: char,
+    /// Config profile: enables and disables validators of the config
+    #[arg(long, requires = "config")]
+    profile: Option<String>,
+
+    /// Frictionless Table Schema (JSON): checks the header, the number of fields, and the types and constraints
+    /// of the columns. Its validators replace those of the config of the same type, the file has a header by default
+    #[arg(long, value_name = "JSON")]
+    table_schema: Option<String>,
+
+    /// CSVW metadata (JSON): the dialect, and the datatypes and constraints of the columns, reported by their CSVW
+    /// names. Alone, reads <FILE>-metadata.json or csv-metadata.json next to the input file, --csvw=<JSON> reads
+    /// another one. Its dialect overrides the config's common settings
+    #[arg(long, value_name = "JSON", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    #[arg(conflicts_with = "table_schema")]
+    csvw: Option<String>,
+
+    /// CSV delimiter [default: ,]
+    #[arg(long)]
+    separator: Option<char>,
+
+    /// Quote char, separators and newlines between quotes are part of the field [default: "]
+    #[arg(long)]
+    quote_char: Option<char>,
+
+    /// The first line is a header
+    #[arg(long)]
+    has_header: bool,
+
+    /// The first line is a record, even when the config says there is a header
+    #[arg(long, conflicts_with = "has_header")]
+    no_header: bool,
 
     /// Output file (defaults to stdout)
     #[arg(short, long)]
     output: Option<String>,
 
+    /// Fix mode: write the corrected data to this file
+    #[arg(long, value_name = "FILE")]
+    fix_output: Option<String>,
+
+    /// Write the records without issues to this file
+    #[arg(long, value_name = "FILE", conflicts_with = "fix_output")]
+    clean_output: Option<String>,
+
+    /// Write the records with issues to this file, with their line number and issues as extra columns
+    #[arg(long, value_name = "FILE")]
+    reject_output: Option<String>,
+
     /// Number of threads
     #[arg(short, long, default_value_t = num_cpus::get())]
     threads: usize,
@@ -205,12 +262,81 @@ struct CliArgs {
     #[arg(short, long, default_value_t = 100_000)]
     batch_size: usize,
 
+    /// Memory map the input file and split it into records on all threads
+    #[arg(long)]
+    mmap: bool,
+
+    /// Maximum number of batches read ahead of the validators (defaults to twice the number of threads)
+    #[arg(long)]
+    max_in_flight: Option<usize>,
+
+    /// Report issues as batches complete, instead of sorted by line: not with outputs to write or unique keys to check
+    #[arg(long)]
+    unordered: bool,
+
+    /// Stop after this many issues
+    #[arg(long, value_name = "N")]
+    max_issues: Option<usize>,
+
+    /// Report at most this many issues per validator
+    #[arg(long, value_name = "N")]
+    max_issues_per_validator: Option<usize>,
+
+    /// Stop at the first issue
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// Don't show the progress bar (only shown when stderr is a terminal)
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Print a summary table (records, issues per validator and per column, throughput) to stderr
+    #[arg(long)]
+    summary: bool,
+
+    /// Severity of the issues found by the validator flags: error (default), warning or info.
+    /// Only errors send records to --reject-output
+    #[arg(long)]
+    severity: Option<Severity>,
+
     #[command(flatten)]
     validator: ValidatorKind,
+
+    #[command(flatten)]
+    fix: FixArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check a config file without validating any input: unknown keys, wrong types, settings that can't work
+    /// or are never used. Exits with an error when the config has errors
+    CheckConfig {
+        #[arg(value_name = "YAML")]
+        config: String,
+    },
+    /// Print the JSON Schema of the config file, for editors to complete and check configs
+    Schema,
+    /// Print a config file as read, environment variables filled in
+    Config {
+        #[arg(value_name = "YAML")]
+        config: String,
+
+        /// Print the config as it runs: includes merged in, and the profile applied
+        #[arg(long)]
+        resolved: bool,
+
+        #[arg(long, requires = "resolved")]
+        profile: Option<String>,
+    },
 }
 
+/// Validators given as flags, none or several; without --config at least one.
 #[derive(Args, Debug)]
-#[group(required = true, multiple = false)]
+#[group(required = false, multiple = true)]
 struct ValidatorKind {
     /// Comma-separated list of illegal characters
     #[arg(long)]
@@ -225,76 +351,229 @@ struct ValidatorKind {
     max_line_length: Option<usize>,
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = CliArgs::parse();
-    let separator = args.separator as u8;
-    let buffer_capacity = 8 * 1024 * 1024;
-
-    let input: Box<dyn Read> = match args.input.as_deref() {
-        Some("-") | None => Box::new(io::stdin()),
-        Some(path) => Box::new(File::open(path)?),
-    };
-
-    let threads = &args.threads;
-    dbg!(&threads);
-
-    let mut reader = OptimizedQuoteAwareReader::new(input, buffer_capacity);
-
-    let validator: Box<dyn Validator> = build_validator_from_args(&args.validator, separator);
-    let validators = Arc::new(vec![validator]);
-
-    let thread_pool = ThreadPoolBuilder::new()
-        .num_threads(args.threads)
-        .build()?;
-
-    let (sender, receiver) = unbounded();
+#[derive(Args, Debug)]
+struct FixArgs {
+    /// Comma-separated replacements for --illegal-chars, used in fix mode
+    #[arg(long, requires = "illegal_chars")]
+    replace_with: Option<String>,
 
-    let mut batch = Vec::with_capacity(args.batch_size);
-    let mut line_buf = Vec::with_capacity(1024);
-    let mut line_number = 0;
+    /// Repair for --field-count in fix mode: pad, truncate, reject or merge:<column name or index>
+    #[arg(long, requires = "field_count")]
+    repair: Option<String>,
+}
 
-    while let Some(line) = reader.next_logical_line(&mut line_buf)? {
-        line_number += 1;
-        batch.push((line_number, line.to_vec()));
+/// The exit status of a validation that reported error issues, 1 is a run that failed.
+const EXIT_INVALID: u8 = 2;
+/// The exit status of a validation that `--fail-fast` or an issue limit stopped early, whatever it found.
+const EXIT_TRUNCATED: u8 = 3;
 
-        if batch.len() >= args.batch_size {
-            execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
+fn main() -> anyhow::Result<ExitCode> {
+    let args = CliArgs::parse();
+    match &args.command {
+        Some(Command::CheckConfig { config }) => return check_config(config).map(|()| ExitCode::SUCCESS),
+        Some(Command::Schema) => {
+            println!("{}", serde_json::to_string_pretty(&schemars::schema_for!(config::ConfigFile))?);
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Config { config, resolved: false, .. }) => {
+            let file: config::ConfigFile = serde_yaml::from_str(&config::interpolate(&fs::read_to_string(config)?)?)?;
+            print!("{}", serde_yaml::to_string(&file)?);
+            return Ok(ExitCode::SUCCESS);
         }
+        Some(Command::Config { config, resolved: true, profile }) => {
+            print!("{}", serde_yaml::to_string(&config::load_config(config, profile.as_deref())?)?);
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
     }
+    let buffer_capacity = 8 * 1024 * 1024;
 
-    if !batch.is_empty() {
-        execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
+    let config = match args.config.as_deref() {
+        Some(path) => config::load_config(path, args.profile.as_deref())?,
+        None => config::ConfigFile::default(),
+    };
+    let mut common = config.common.unwrap_or_default();
+    let mut validators = config.validators;
+    if let Some(path) = args.table_schema.as_deref() {
+        let (specs, warnings) = table_schema::validators(path, args.input.as_deref())?;
+        for warning in warnings {
+            eprintln!("warning: {}: {}", path, warning);
+        }
+        validators = config::replace_validators(validators, specs);
+        common = common.or(&CommonSettings { has_header: Some(true), ..Default::default() });
+    }
+    if let Some(csvw) = args.csvw.as_deref() {
+        let path = match (csvw, args.input.as_deref()) {
+            ("", Some(input)) => csvw::metadata_path(input)?,
+            ("", None) => anyhow::bail!("--csvw looks for the metadata next to the input file, on stdin give --csvw=<JSON>"),
+            (path, _) => PathBuf::from(path),
+        };
+        let metadata = csvw::load(&path, args.input.as_deref())?;
+        for warning in metadata.warnings {
+            eprintln!("warning: {}: {}", path.display(), warning);
+        }
+        validators = config::replace_validators(validators, metadata.validators);
+        common = metadata.common.or(&common);
     }
+    // flags over the validators' own common settings, over the top-level ones, over the defaults
+    let flags = CommonSettings {
+        separator: args.separator,
+        quote_char: args.quote_char,
+        has_header: (args.has_header || args.no_header).then_some(args.has_header),
+        skip_rows: None,
+    };
+
+    let flag_specs = specs_from_args(&args.validator, &args.fix)?
+        .into_iter()
+        .map(|spec| spec.with_severity(args.severity.unwrap_or_default()));
+    let specs = config::replace_validators(validators, flag_specs);
+    let dialect = Dialect::resolve(&flags, &common, &specs)?;
+    let Dialect { separator, quote_char, has_header, skip_rows } = dialect;
+    let validators = into_validators(specs, &dialect)?;
+    anyhow::ensure!(
+        !validators.is_empty(),
+        "no validator to run: give --config, --table-schema, --csvw, or --illegal-chars, --field-count or --max-line-length"
+    );
+    let validators = Arc::new(validators);
 
-    drop(sender);
+    let progress_bar = if args.no_progress { None } else { ProgressBar::on_stderr().map(Arc::new) };
+    let on_progress = progress_bar.clone().map(|bar| -> ProgressCallback { Arc::new(move |progress| bar.update(progress)) });
+
+    let options = ValidationOptions {
+        threads: args.threads,
+        thread_pool: None,
+        batch_size: args.batch_size,
+        buffer_size: buffer_capacity,
+        mmap: args.mmap,
+        max_in_flight: args.max_in_flight,
+        separator,
+        quote_char,
+        has_header,
+        skip_rows,
+        preserve_order: !args.unordered,
+        fix_output: args.fix_output.clone(),
+        clean_output: args.clean_output.clone(),
+        reject_output: args.reject_output.clone(),
+        max_issues: args.max_issues,
+        max_issues_per_validator: args.max_issues_per_validator,
+        fail_fast: args.fail_fast,
+        cancellation: None,
+        timeout: args.timeout.map(Duration::try_from_secs_f64).transpose()?,
+        on_progress,
+    };
 
     let mut writer: Box<dyn Write> = match args.output.as_deref() {
         Some("-") | None => Box::new(io::stdout()),
-        Some(path) => Box::new(File::create(path)?),
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
     };
+    // issues printed to the terminal the progress bar is on replace the bar, until its next update
+    let shared_terminal = progress_bar.filter(|_| matches!(args.output.as_deref(), Some("-") | None) && io::stdout().is_terminal());
 
-    for issues in receiver.iter() {
+    // issues are written out as the batches complete
+    let mut write_issues = |issues: Vec<ValidationIssue>| -> io::Result<()> {
+        if let Some(bar) = &shared_terminal {
+            bar.clear();
+        }
         for issue in issues {
-            writeln!(
+            write!(
                 writer,
-                "[{}] Line {}, Position {:?}: {}",
-                issue.validator, issue.line_number, issue.position, issue.message
+                "[{}] {} {} Line {}, Position {:?}: {}",
+                issue.validator,
+                issue.severity.as_str(),
+                issue.code.code,
+                issue.line_number,
+                issue.position,
+                issue.message
             )?;
+            if issue.status != IssueStatus::Open {
+                write!(writer, " ({})", issue.status.as_str())?;
+            }
+            writeln!(writer)?;
         }
+        Ok(())
+    };
+
+    let report = match args.input.as_deref() {
+        Some("-") | None => {
+            anyhow::ensure!(!args.mmap, "--mmap needs an input file");
+            validate_reader_with_sink(io::stdin(), validators, options, &mut write_issues)?
+        }
+        Some(path) => validate_file_with_sink(path, validators, options, &mut write_issues)?,
+    };
+    writer.flush()?;
+
+    if report.truncated {
+        eprintln!("Issue limit reached, validation stopped early: the report is truncated");
+    }
+    if args.summary {
+        summary::write_summary(&mut io::stderr().lock(), &report)?;
     }
 
+    Ok(if report.truncated {
+        ExitCode::from(EXIT_TRUNCATED)
+    } else if report.issues_per_severity.contains_key(&Severity::Error) {
+        ExitCode::from(EXIT_INVALID)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Prints the findings as `file:line:column: severity: message`, those of the files the config includes too.
+fn check_config(path: &str) -> anyhow::Result<()> {
+    let mut errors = 0;
+    let mut warnings = 0;
+    for (file, findings) in config::check_config(path)? {
+        for finding in findings {
+            println!("{}:{}", file.display(), finding);
+            match finding.severity {
+                Severity::Error => errors += 1,
+                _ => warnings += 1,
+            }
+        }
+    }
+    // the files are fine on their own, are they together?
+    if errors == 0 {
+        for error in config::check_resolved(path)? {
+            println!("{}: error: {}", path, error);
+            errors += 1;
+        }
+    }
+    anyhow::ensure!(errors == 0, "{}: {} error(s), {} warning(s)", path, errors, warnings);
+    println!("{}: ok, {} warning(s)", path, warnings);
     Ok(())
 }
 
-fn build_validator_from_args(kind: &ValidatorKind, separator: u8) -> Box<dyn Validator> {
+/// The validators picked on the command line, as the same specs a config file holds.
+fn specs_from_args(kind: &ValidatorKind, fix: &FixArgs) -> anyhow::Result<Vec<ValidatorSpec>> {
+    let split = |list: &str| list.split(',').map(|s| s.trim().to_string()).collect::<Vec<_>>();
+    let mut specs = Vec::new();
     if let Some(chars) = &kind.illegal_chars {
-        let list: Vec<&str> = chars.split(',').map(str::trim).collect();
-        Box::new(IllegalCharactersValidator::new( &list))
-    } else if let Some(count) = kind.field_count {
-        Box::new(FieldCountValidator::new( count, separator))
-    } else if let Some(max) = kind.max_line_length {
-        Box::new(LineLengthValidator::new( max))
-    } else {
-        panic!("No validator specified")
+        let list = split(chars);
+        specs.push(match &fix.replace_with {
+            Some(replace_with) => {
+                let replacements = split(replace_with);
+                anyhow::ensure!(
+                    list.len() == replacements.len(),
+                    "--illegal-chars and --replace-with must have the same number of entries"
+                );
+                ValidatorSpec::new_illegal_chars_with_replacements(list, replacements)
+            }
+            None => ValidatorSpec::new_illegal_chars(list),
+        });
     }
+    if let Some(count) = kind.field_count {
+        specs.push(match fix.repair.as_deref() {
+            Some(repair) => ValidatorSpec::new_field_count_with_repair(count, parse_repair(repair)?),
+            None => ValidatorSpec::new_field_count(count),
+        });
+    }
+    if let Some(max) = kind.max_line_length {
+        specs.push(ValidatorSpec::new_line_length(max));
+    }
+    Ok(specs)
+}
+
+/// `merge:<column>` takes either a column index or a column name, looked up in the header by the engine.
+fn parse_repair(repair: &str) -> anyhow::Result<FieldCountRepair> {
+    repair.parse().map_err(|e| anyhow::anyhow!("invalid --repair: {}", e))
 }
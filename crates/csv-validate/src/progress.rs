@@ -0,0 +1,79 @@
+use csv_validator_core::Progress;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Mutex, PoisonError};
+
+const BAR_WIDTH: usize = 30;
+
+/// A progress bar on stderr, redrawn in place.
+pub struct ProgressBar {
+    /// a bar is on screen, and has to be cleared before printing anything else to the terminal
+    drawn: Mutex<bool>,
+}
+
+impl ProgressBar {
+    /// None when stderr isn't a terminal: redirected output gets no progress bar.
+    pub fn on_stderr() -> Option<Self> {
+        io::stderr().is_terminal().then(|| Self { drawn: Mutex::new(false) })
+    }
+
+    pub fn update(&self, progress: &Progress) {
+        let mut drawn = self.drawn.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{}", render(progress));
+        if progress.finished {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+        *drawn = !progress.finished;
+    }
+
+    /// Clears the bar, ie. before printing issues to the same terminal. The next update draws it again.
+    pub fn clear(&self) {
+        let mut drawn = self.drawn.lock().unwrap_or_else(PoisonError::into_inner);
+        if *drawn {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+            *drawn = false;
+        }
+    }
+}
+
+fn render(progress: &Progress) -> String {
+    let mut line = match (progress.fraction(), progress.total_bytes) {
+        (Some(fraction), Some(total)) => {
+            let filled = (fraction * BAR_WIDTH as f64) as usize;
+            format!(
+                "[{}{}] {:>3.0}% {} / {}",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                fraction * 100.0,
+                human_bytes(progress.bytes as f64),
+                human_bytes(total as f64),
+            )
+        }
+        _ => human_bytes(progress.bytes as f64),
+    };
+    line.push_str(&format!(
+        ", {} records, {} issues, {}/s",
+        progress.records,
+        progress.issues,
+        human_bytes(progress.throughput())
+    ));
+    line
+}
+
+pub fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value as u64, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
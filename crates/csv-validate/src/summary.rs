@@ -0,0 +1,80 @@
+use crate::progress::human_bytes;
+use csv_validator_core::ValidationReport;
+use std::io::{self, Write};
+
+/// The report as a table: totals first, then the issues per validator and per column.
+pub fn write_summary<W: Write>(out: &mut W, report: &ValidationReport) -> io::Result<()> {
+    let rows = [
+        ("Records", report.records.to_string()),
+        ("Bytes", human_bytes(report.bytes as f64)),
+        (
+            "Records with issues",
+            format!("{} ({:.2}%)", report.records_with_issues, report.issue_rate() * 100.0),
+        ),
+        ("Issues", report.total_issues().to_string()),
+        ("Elapsed", format!("{:.2}s", report.elapsed.as_secs_f64())),
+        (
+            "Throughput",
+            format!("{}/s, {:.0} records/s", human_bytes(report.throughput()), report.records_per_second()),
+        ),
+        ("Truncated", if report.truncated { "yes" } else { "no" }.to_string()),
+    ];
+    write_table(out, None, &rows)?;
+
+    if !report.issues_per_validator.is_empty() {
+        let rows: Vec<_> = report
+            .issues_per_validator
+            .iter()
+            .map(|(validator, count)| (validator.to_string(), count.to_string()))
+            .collect();
+        writeln!(out)?;
+        write_table(out, Some(("Validator", "Issues")), &rows)?;
+    }
+
+    if !report.issues_per_severity.is_empty() {
+        // most severe first
+        let rows: Vec<_> = report
+            .issues_per_severity
+            .iter()
+            .rev()
+            .map(|(severity, count)| (severity.as_str(), count.to_string()))
+            .collect();
+        writeln!(out)?;
+        write_table(out, Some(("Severity", "Issues")), &rows)?;
+    }
+
+    if !report.issues_per_column.is_empty() {
+        let rows: Vec<_> = report
+            .issues_per_column
+            .iter()
+            .map(|(&column, count)| {
+                let name = match report.column_name(column) {
+                    Some(name) => format!("{} ({})", name, column),
+                    None => column.to_string(),
+                };
+                (name, count.to_string())
+            })
+            .collect();
+        writeln!(out)?;
+        write_table(out, Some(("Column", "Issues")), &rows)?;
+    }
+    Ok(())
+}
+
+fn write_table<W: Write, K: AsRef<str>, V: AsRef<str>>(
+    out: &mut W,
+    header: Option<(&str, &str)>,
+    rows: &[(K, V)],
+) -> io::Result<()> {
+    let width = rows.iter().map(|(key, _)| key.as_ref().len()).chain(header.map(|h| h.0.len())).max().unwrap_or_default();
+
+    if let Some((key, value)) = header {
+        let value_width = rows.iter().map(|(_, value)| value.as_ref().len()).chain([value.len()]).max().unwrap_or_default();
+        writeln!(out, "{:<width$}  {}", key, value)?;
+        writeln!(out, "{}", "-".repeat(width + 2 + value_width))?;
+    }
+    for (key, value) in rows {
+        writeln!(out, "{:<width$}  {}", key.as_ref(), value.as_ref())?;
+    }
+    Ok(())
+}
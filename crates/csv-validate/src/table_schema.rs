@@ -0,0 +1,241 @@
+use csv_validator_core::{Column, ColumnType, ForeignKey, Severity, ValidatorSpec};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// A Frictionless Table Schema, the parts of it that translate to validators.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TableSchema {
+    fields: Vec<Field>,
+    #[serde(default)]
+    primary_key: Option<Names>,
+    #[serde(default)]
+    foreign_keys: Vec<SchemaForeignKey>,
+    #[serde(default)]
+    missing_values: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Field {
+    name: String,
+    #[serde(default = "string_type", rename = "type")]
+    datatype: String,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    constraints: Constraints,
+    /// the other properties, only checked for those that change how values are read
+    #[serde(flatten)]
+    options: serde_json::Map<String, Value>,
+}
+
+fn string_type() -> String {
+    "string".to_string()
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Constraints {
+    #[serde(default)]
+    required: bool,
+    #[serde(default)]
+    unique: bool,
+    #[serde(default, rename = "enum")]
+    allowed: Option<Vec<Value>>,
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(default)]
+    minimum: Option<Value>,
+    #[serde(default)]
+    maximum: Option<Value>,
+    #[serde(default)]
+    min_length: Option<usize>,
+    #[serde(default)]
+    max_length: Option<usize>,
+}
+
+/// One field name, or several.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Names {
+    One(String),
+    Several(Vec<String>),
+}
+
+impl Names {
+    pub(crate) fn into_vec(self) -> Vec<String> {
+        match self {
+            Names::One(name) => vec![name],
+            Names::Several(names) => names,
+        }
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<String> {
+        match self {
+            Names::One(name) => vec![name.clone()],
+            Names::Several(names) => names.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SchemaForeignKey {
+    fields: Names,
+    reference: Reference,
+}
+
+#[derive(Deserialize)]
+struct Reference {
+    /// `""` for the table itself
+    #[serde(default)]
+    resource: String,
+    fields: Names,
+}
+
+/// The `datapackage.json` next to a schema, which names the resources foreign keys refer to.
+#[derive(Deserialize)]
+struct DataPackage {
+    #[serde(default)]
+    resources: Vec<PackageResource>,
+}
+
+#[derive(Deserialize)]
+struct PackageResource {
+    name: String,
+    /// a path relative to the package, or a list of them for a resource in several files
+    #[serde(default)]
+    path: Value,
+}
+
+/// The file of a foreign key's `resource`, the schema in `dir`.
+fn resource_path(dir: &Path, resource: &str) -> anyhow::Result<String> {
+    let package_path = dir.join("datapackage.json");
+    if !package_path.exists() {
+        let path = dir.join(resource);
+        anyhow::ensure!(
+            path.is_file(),
+            "the foreign key resource '{}' is no file next to the table schema, and there's no datapackage.json to name it",
+            resource
+        );
+        return Ok(path.display().to_string());
+    }
+
+    let package = package_path.display();
+    let source = fs::read_to_string(&package_path).map_err(|e| anyhow::anyhow!("can't read {}: {}", package, e))?;
+    let package_file: DataPackage = serde_json::from_str(&source).map_err(|e| anyhow::anyhow!("invalid data package {}: {}", package, e))?;
+    let Some(found) = package_file.resources.iter().find(|found| found.name == resource) else {
+        let names: Vec<_> = package_file.resources.iter().map(|found| found.name.as_str()).collect();
+        anyhow::bail!("the foreign key resource '{}' isn't in {}, it has: {}", resource, package, names.join(", "));
+    };
+    match &found.path {
+        Value::String(path) if !path.contains("://") => Ok(dir.join(path).display().to_string()),
+        _ => anyhow::bail!("the foreign key resource '{}' of {} isn't one local file, which is all a reference can be", resource, package),
+    }
+}
+
+/// Field properties that change how values are read, which the validators only read in the default way.
+const READ_OPTIONS: &[&str] = &["trueValues", "falseValues", "decimalChar", "groupChar", "bareNumber"];
+
+/// The validators of the Table Schema at `path`, for the file at `input`: the number of fields, and a columns
+/// validator checking the header, the types and constraints of the fields, the primary key and the foreign keys.
+/// Also returns warnings about what the validators don't check: other types and formats are read as strings.
+///
+/// A foreign key's `resource` is the name of a resource of the `datapackage.json` next to the schema, `""` for `input`
+/// itself. Without a data package, it's the path of the referenced CSV file, relative to the schema.
+pub fn validators(path: &str, input: Option<&str>) -> anyhow::Result<(Vec<ValidatorSpec>, Vec<String>)> {
+    let source = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("can't read table schema {}: {}", path, e))?;
+    let schema: TableSchema =
+        serde_json::from_str(&source).map_err(|e| anyhow::anyhow!("invalid table schema {}: {}", path, e))?;
+    anyhow::ensure!(!schema.fields.is_empty(), "invalid table schema {}: no fields", path);
+
+    let mut warnings = Vec::new();
+    let mut columns = Vec::with_capacity(schema.fields.len());
+    for field in schema.fields {
+        let (datatype, warning) = column_type(&field);
+        warnings.extend(warning.map(|warning| format!("field '{}': {}, its values are read as strings", field.name, warning)));
+        let constraints = field.constraints;
+        let bound = |value: Option<Value>| value.map(|value| text(&value));
+        columns.push(Column {
+            datatype,
+            required: constraints.required,
+            unique: constraints.unique,
+            allowed: constraints.allowed.map(|values| values.iter().map(text).collect()),
+            pattern: constraints.pattern,
+            // bounds only apply to values read as their type
+            minimum: bound(constraints.minimum).filter(|_| datatype != ColumnType::String),
+            maximum: bound(constraints.maximum).filter(|_| datatype != ColumnType::String),
+            min_length: constraints.min_length,
+            max_length: constraints.max_length,
+            name: field.name,
+            ..Default::default()
+        });
+    }
+
+    let mut unique_keys = Vec::new();
+    if let Some(primary_key) = schema.primary_key {
+        let primary_key = primary_key.into_vec();
+        // the fields of a primary key can't be missing
+        for column in columns.iter_mut().filter(|column| primary_key.contains(&column.name)) {
+            column.required = true;
+        }
+        // a unique field already is a key of its own
+        if !matches!(&primary_key[..], [name] if columns.iter().any(|column| column.name == *name && column.unique)) {
+            unique_keys.push(primary_key);
+        }
+    }
+
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let foreign_keys = schema
+        .foreign_keys
+        .into_iter()
+        .map(|key| {
+            let reference = match key.reference.resource.as_str() {
+                "" => input.map(str::to_string).ok_or_else(|| {
+                    anyhow::anyhow!("the table schema has a foreign key to the file itself, which needs an input file, not stdin")
+                })?,
+                resource => resource_path(dir, resource)?,
+            };
+            Ok(ForeignKey { columns: key.fields.into_vec(), reference, reference_columns: key.reference.fields.into_vec() })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let field_count = ValidatorSpec::new_field_count(columns.len());
+    let columns = ValidatorSpec::Columns {
+        id: None,
+        columns,
+        missing_values: schema.missing_values.unwrap_or_else(|| vec![String::new()]),
+        unique_keys,
+        foreign_keys,
+        enabled: true,
+        severity: Severity::Error,
+        common: None,
+    };
+    Ok((vec![field_count, columns], warnings))
+}
+
+/// The column type of a field, with a warning when its values aren't read the way the schema says.
+fn column_type(field: &Field) -> (ColumnType, Option<String>) {
+    let datatype = match field.datatype.parse::<ColumnType>() {
+        Ok(datatype) => datatype,
+        Err(_) if field.datatype == "any" => return (ColumnType::String, None),
+        Err(_) => return (ColumnType::String, Some(format!("type '{}' isn't supported", field.datatype))),
+    };
+    if let Some(format) = field.format.as_deref().filter(|format| *format != "default") {
+        return (ColumnType::String, Some(format!("format '{}' isn't supported", format)));
+    }
+    match READ_OPTIONS.iter().find(|option| field.options.contains_key(**option)) {
+        Some(option) => (ColumnType::String, Some(format!("'{}' isn't supported", option))),
+        None => (datatype, None),
+    }
+}
+
+/// A JSON value as the text it matches in the file, ie. `10` for the number 10.
+pub(crate) fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{csv_validate, temp_dir};
+
+/// Runs `check-config` on `yaml`, returns whether it passed and what it printed.
+fn check_config(name: &str, yaml: &str) -> (bool, String) {
+    let dir = temp_dir(name);
+    std::fs::write(dir.join("config.yaml"), yaml).unwrap();
+    let output = csv_validate(&dir, &["check-config", "config.yaml"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn unknown_keys_and_types() {
+    let (ok, findings) = check_config(
+        "lint-unknown",
+        "common:\n  separator: ';'\n  has_heder: true\n\
+         validators:\n  - type: field_count\n    expected: 3\n    repiar: pad\n  - type: line_lenght\n    max_length: 10\n",
+    );
+    assert!(!ok);
+    assert_eq!(
+        findings,
+        "config.yaml:3:3: error: unknown key `has_heder` in `common`, expected one of: has_header, quote_char, separator, skip_rows (did you mean `has_header`?)\n\
+         config.yaml:7:5: error: unknown key `repiar` in the field_count validator, expected one of: common, enabled, expected, id, repair, severity, type (did you mean `repair`?)\n\
+         config.yaml:8:11: error: unknown validator type `line_lenght`, expected one of: illegal_chars, field_count, line_length, columns (did you mean `line_length`?)\n"
+    );
+}
+
+#[test]
+fn values_of_the_wrong_type() {
+    let (ok, findings) = check_config(
+        "lint-types",
+        "common:\n  separator: ';;'\n  has_header: yes\n  skip_rows: -1\n\
+         validators:\n  - type: field_count\n    expected: many\n    enabled: 1\n  - type: illegal_chars\n    illegal_chars: '@'\n",
+    );
+    assert!(!ok);
+    assert_eq!(
+        findings,
+        "config.yaml:2:14: error: `separator` must be a single character, got `;;`\n\
+         config.yaml:3:15: error: `has_header` must be true or false, got `yes`\n\
+         config.yaml:4:14: error: `skip_rows` must be a whole number, got `-1`\n\
+         config.yaml:7:15: error: `expected` must be a whole number, got `many`\n\
+         config.yaml:8:14: error: `enabled` must be true or false, got `1`\n\
+         config.yaml:10:20: error: expected a list for `illegal_chars`, got `@`\n"
+    );
+}
+
+#[test]
+fn replacements_match_the_patterns() {
+    let (ok, findings) = check_config(
+        "lint-replacements",
+        "validators:\n  - type: illegal_chars\n    illegal_chars: ['@', '!', '?']\n    replace_with: ['-', '.']\n",
+    );
+    assert!(!ok);
+    assert_eq!(
+        findings,
+        "config.yaml:4:5: error: `replace_with` has 2 entries and `illegal_chars` 3: each pattern is replaced by the entry at its position\n"
+    );
+}
+
+#[test]
+fn empty_patterns() {
+    let (ok, findings) = check_config(
+        "lint-empty",
+        "validators:\n  - type: illegal_chars\n    illegal_chars: ['@', '']\n  - type: illegal_chars\n    illegal_chars: []\n",
+    );
+    assert!(!ok);
+    assert_eq!(
+        findings,
+        "config.yaml:3:26: error: empty pattern, it would match everywhere\n\
+         config.yaml:5:5: error: `illegal_chars` is empty, the validator would never find anything\n"
+    );
+}
+
+#[test]
+fn invalid_column_pattern() {
+    let (ok, findings) = check_config(
+        "lint-regex",
+        "validators:\n  - type: columns\n    columns:\n      - name: id\n        type: integer\n      - name: code\n        pattern: '[A-Z'\n",
+    );
+    assert!(!ok);
+    assert!(
+        findings.starts_with("config.yaml:6:9: error: invalid configuration: column 'code': invalid pattern: regex parse error:"),
+        "{}",
+        findings
+    );
+    assert_eq!(findings.lines().filter(|line| line.starts_with("config.yaml:")).count(), 1, "{}", findings);
+}
+
+#[test]
+fn unused_settings_are_warnings() {
+    let (ok, findings) = check_config(
+        "lint-unused",
+        "common:\n  separator: ';'\n  has_header: true\n\
+         validators:\n  - type: illegal_chars\n    illegal_chars: ['@', '!', '@']\n    common:\n      separator: ','\n\
+         \x20 - type: line_length\n    max_length: 10\n    common:\n      separator: ','\n\
+         \x20 - type: field_count\n    expected: 3\n    enabled: false\n",
+    );
+    assert!(ok);
+    assert_eq!(
+        findings,
+        "config.yaml:2:3: warning: `separator` is never used, every enabled validator sets its own\n\
+         config.yaml:6:31: warning: '@' is listed twice, the first one always matches: this one is never used\n\
+         config.yaml: ok, 2 warning(s)\n"
+    );
+}
+
+#[test]
+fn foreign_key_references_are_not_read() {
+    // the reference is only read when the validator runs, ie. where the file is
+    let (ok, findings) = check_config(
+        "lint-reference",
+        "validators:\n  - type: columns\n    columns:\n      - name: country\n\
+         \x20   foreign_keys:\n      - columns: [country]\n        reference: countries.csv\n        reference_columns: [code]\n",
+    );
+    assert!(ok, "{}", findings);
+    assert_eq!(findings, "config.yaml: ok, 0 warning(s)\n");
+}
@@ -0,0 +1,32 @@
+// each test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// An empty directory for the files of one test, named after it.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("csv-validate-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `csv-validate` run in `dir`.
+pub fn command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_csv-validate"));
+    command.current_dir(dir);
+    command
+}
+
+/// Runs `csv-validate` in `dir`, without the progress bar when validating.
+pub fn csv_validate(dir: &Path, args: &[&str]) -> Output {
+    let mut command = command(dir);
+    command.args(args);
+    if !matches!(args.first(), Some(&("check-config" | "config" | "schema"))) {
+        command.arg("--no-progress");
+    }
+    command.output().unwrap()
+}
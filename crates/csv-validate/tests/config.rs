@@ -0,0 +1,173 @@
+mod common;
+
+use common::{csv_validate, temp_dir};
+use std::path::Path;
+use std::process::Output;
+
+/// `csv-validate` with the environment the feed configs read.
+fn csv_validate_feed(dir: &Path, args: &[&str]) -> Output {
+    common::command(dir).args(args).env("FEED_MAX_LINE", "80").env_remove("FEED_SEPARATOR").output().unwrap()
+}
+
+#[test]
+fn includes_profiles_and_environment_variables_resolve() {
+    let dir = temp_dir("config");
+    std::fs::create_dir_all(dir.join("shared")).unwrap();
+    std::fs::write(
+        dir.join("shared/base.yaml"),
+        "common:\n  separator: ';'\n  has_header: true\n\
+         validators:\n  - type: illegal_chars\n    id: chars\n    illegal_chars: ['@']\n  - type: line_length\n    max_length: ${FEED_MAX_LINE}\n    enabled: false\n\
+         profiles:\n  strict:\n    enable: [line_length]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("feed.yaml"),
+        "include: [shared/base.yaml]\ncommon:\n  separator: '${FEED_SEPARATOR:-|}'\n\
+         validators:\n  - type: field_count\n    expected: 3\n  - type: illegal_chars\n    id: chars\n    illegal_chars: ['!']\n",
+    )
+    .unwrap();
+
+    let output = csv_validate_feed(&dir, &["config", "feed.yaml", "--resolved", "--profile", "strict"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "common:\n  separator: '|'\n  has_header: true\n\
+         validators:\n\
+         - type: illegal_chars\n  id: chars\n  illegal_chars:\n  - '!'\n  enabled: true\n  severity: error\n\
+         - type: line_length\n  enabled: true\n  max_length: 80\n  severity: error\n\
+         - type: field_count\n  expected: 3\n  enabled: true\n  severity: error\n\
+         profiles:\n  strict:\n    enable:\n    - line_length\n"
+    );
+
+    let output = csv_validate_feed(&dir, &["config", "feed.yaml", "--resolved", "--profile", "lenient"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no profile 'lenient' in the config, it has: strict"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn included_validators_are_replaced_by_id_only() {
+    let dir = temp_dir("config-ids");
+    std::fs::write(
+        dir.join("base.yaml"),
+        "validators:\n  - type: illegal_chars\n    id: symbols\n    illegal_chars: ['@']\n  - type: illegal_chars\n    id: words\n    illegal_chars: [tv]\n\
+         profiles:\n  no-words:\n    disable: [words]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("feed.yaml"),
+        "include: [base.yaml]\n\
+         validators:\n  - type: illegal_chars\n    id: symbols\n    illegal_chars: ['!']\n  - type: illegal_chars\n    illegal_chars: ['#']\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("data.csv"), "a@b!\ntv#\n").unwrap();
+
+    let output = csv_validate(&dir, &["check-config", "feed.yaml"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    // `symbols` is replaced, `words` is kept and the validator without an id is added
+    let output = csv_validate(&dir, &["config", "feed.yaml", "--resolved"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "validators:\n\
+         - type: illegal_chars\n  id: symbols\n  illegal_chars:\n  - '!'\n  enabled: true\n  severity: error\n\
+         - type: illegal_chars\n  id: words\n  illegal_chars:\n  - tv\n  enabled: true\n  severity: error\n\
+         - type: illegal_chars\n  illegal_chars:\n  - '#'\n  enabled: true\n  severity: error\n\
+         profiles:\n  no-words:\n    disable:\n    - words\n"
+    );
+
+    let output = csv_validate(&dir, &["--config", "feed.yaml", "--profile", "no-words", "data.csv"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[illegal_characters] error IC001 Line 1, Position Some(3): Illegal character(s) '!'\n\
+         [illegal_characters] error IC001 Line 2, Position Some(2): Illegal character(s) '#'\n"
+    );
+
+    std::fs::write(dir.join("feed.yaml"), "validators:\n  - type: line_length\n    id: width\n    max_length: 80\n  - type: field_count\n    id: width\n    expected: 2\n").unwrap();
+    let output = csv_validate(&dir, &["check-config", "feed.yaml"]);
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("feed.yaml:6:9: error: duplicate validator id 'width'\n"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn deprecated_fix_key_still_loads() {
+    let dir = temp_dir("fix-key");
+    std::fs::write(
+        dir.join("config.yaml"),
+        "validators:\n  - type: illegal_chars\n    illegal_chars: ['@']\n    fix: false\n    enabled: true\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("data.csv"), "a@b\n").unwrap();
+
+    let output = csv_validate(&dir, &["check-config", "config.yaml"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "config.yaml:4:5: warning: `fix` is deprecated and ignored: fix mode is on with --fix-output, and uses `replace_with`\n\
+         config.yaml: ok, 1 warning(s)\n"
+    );
+
+    let output = csv_validate(&dir, &["--config", "config.yaml", "data.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[illegal_characters] error IC001 Line 1, Position Some(1): Illegal character(s) '@'\n");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn config_validators_and_flags_run_together() {
+    let dir = temp_dir("config-flags");
+    std::fs::write(
+        dir.join("config.yaml"),
+        "common:\n  separator: ';'\n  has_header: true\n  skip_rows: 1\n\
+         validators:\n  - type: illegal_chars\n    illegal_chars: ['@']\n  - type: field_count\n    expected: 3\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("data.csv"), "Export;2024\nid;comment;amount\n1;a@;2\n2;b;c;3\n").unwrap();
+
+    let output = csv_validate(&dir, &["--config", "config.yaml", "data.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[illegal_characters] error IC001 Line 3, Position Some(3): Illegal character(s) '@'\n\
+         [field_count] error FC001 Line 4, Position None: Expected 3 fields, found 4\n"
+    );
+
+    // a validator flag replaces the config's validator of its type, and adds one of a new type
+    let output = csv_validate(&dir, &["--config", "config.yaml", "data.csv", "--field-count", "4", "--max-line-length", "6"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[field_count] error FC001 Line 2, Position None: Expected 4 fields, found 3\n\
+         [line_length] error LL001 Line 2, Position None: Line length 17 exceeds maximum 6\n\
+         [illegal_characters] error IC001 Line 3, Position Some(3): Illegal character(s) '@'\n\
+         [field_count] error FC001 Line 3, Position None: Expected 4 fields, found 3\n\
+         [line_length] error LL001 Line 4, Position None: Line length 7 exceeds maximum 6\n"
+    );
+
+    // the dialect flags override the config's common settings
+    let output = csv_validate(&dir, &["--config", "config.yaml", "data.csv", "--separator", ","]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[field_count] error FC001 Line 2, Position None: Expected 3 fields, found 1\n\
+         [illegal_characters] error IC001 Line 3, Position Some(3): Illegal character(s) '@'\n\
+         [field_count] error FC001 Line 3, Position None: Expected 3 fields, found 1\n\
+         [field_count] error FC001 Line 4, Position None: Expected 3 fields, found 1\n"
+    );
+
+    // a repair merges into the column named in the header, after the config's skipped rows
+    let output = csv_validate(
+        &dir,
+        &["--config", "config.yaml", "data.csv", "--field-count", "3", "--repair", "merge:comment", "--fix-output", "fixed.csv"],
+    );
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("fixed.csv")).unwrap(),
+        "Export;2024\nid;comment;amount\n1;a@;2\n2;\"b;c\";3\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{csv_validate, temp_dir};
+
+#[test]
+fn csvw_metadata_gives_dialect_and_columns() {
+    let dir = temp_dir("csvw");
+    std::fs::write(
+        dir.join("csv-metadata.json"),
+        r#"{
+          "@context": "http://www.w3.org/ns/csvw",
+          "dialect": {"delimiter": ";", "skipRows": 1},
+          "tables": [
+            {"url": "regions.csv", "tableSchema": {"columns": [{"name": "code", "titles": "Code"}]}},
+            {
+              "url": "towns.csv",
+              "null": "-",
+              "tableSchema": {
+                "columns": [
+                  {"name": "town_id", "titles": "Town", "datatype": {"base": "string", "format": "[0-9]{5}"}, "required": true},
+                  {"name": "region", "titles": "Region"},
+                  {"name": "population", "titles": {"en": "Population"}, "datatype": "nonNegativeInteger"},
+                  {"name": "uri", "virtual": true}
+                ],
+                "primaryKey": "town_id",
+                "foreignKeys": [{"columnReference": "region", "reference": {"resource": "regions.csv", "columnReference": "code"}}]
+              }
+            }
+          ]
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("regions.csv"), "Source: census\nCode\n11\n84\n").unwrap();
+    std::fs::write(dir.join("towns.csv"), "Source: census\nTown;Region;Population\n75056;11;-\n7505;99;-5\n75056;84;1\n").unwrap();
+
+    let output = csv_validate(&dir, &["--csvw", "towns.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[columns] error CV005 Line 4, Position Some(0): Column 'town_id': '7505' doesn't match the pattern [0-9]{5}\n\
+         [columns] error CV006 Line 4, Position Some(8): Column 'population': -5 is below the minimum 0\n\
+         [columns] error CV008 Line 4, Position Some(5): Column(s) region: '99' is not in regions.csv (Code)\n\
+         [columns] error CV003 Line 5, Position Some(0): Column(s) town_id: '75056' is not unique, also on line 3\n"
+    );
+
+    // an explicit path, and the metadata of a single table
+    std::fs::write(
+        dir.join("meta.json"),
+        r#"{"url": "towns.csv", "dialect": {"delimiter": ";", "headerRowCount": 2}, "tableSchema": {"columns": [{"titles": "Town"}, {"name": "region"}]}}"#,
+    )
+    .unwrap();
+    let output = csv_validate(&dir, &["--csvw=meta.json", "towns.csv"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[field_count] error FC001 Line 2, Position None: Expected 2 fields, found 3\n\
+         [columns] error HD001 Line 2, Position Some(5): Header column 'Region' should be 'region'\n\
+         [field_count] error FC001 Line 3, Position None: Expected 2 fields, found 3\n\
+         [field_count] error FC001 Line 4, Position None: Expected 2 fields, found 3\n\
+         [field_count] error FC001 Line 5, Position None: Expected 2 fields, found 3\n"
+    );
+
+    let output = csv_validate(&dir, &["--csvw", "regions-2024.csv"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no table for regions-2024.csv, it has: regions.csv, towns.csv"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn crlf_files_and_line_terminators() {
+    let dir = temp_dir("csvw-crlf");
+    let metadata = |terminators: &str| {
+        format!(
+            r#"{{
+              "url": "people.csv",
+              "dialect": {{"lineTerminators": {}}},
+              "tableSchema": {{
+                "columns": [{{"name": "name", "titles": "Name"}}, {{"name": "age", "titles": "Age", "datatype": "integer"}}],
+                "primaryKey": "age"
+              }}
+            }}"#,
+            terminators
+        )
+    };
+    std::fs::write(dir.join("people.csv"), "Name,Age\r\nann,30\r\nbob,x\r\ncid,30\r\n").unwrap();
+
+    std::fs::write(dir.join("people.csv-metadata.json"), metadata(r#"["\r\n", "\n"]"#)).unwrap();
+    let output = csv_validate(&dir, &["--csvw", "people.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[columns] error CV001 Line 3, Position Some(4): Column 'age': 'x' is not a valid integer\n\
+         [columns] error CV003 Line 4, Position Some(4): Column(s) age: '30' is not unique, also on line 2\n"
+    );
+
+    std::fs::write(dir.join("people.csv-metadata.json"), metadata(r#""\r""#)).unwrap();
+    let output = csv_validate(&dir, &["--csvw", "people.csv"]);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains(r#"dialect lineTerminators "\r" isn't supported, lines end at \n or \r\n"#),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{csv_validate, temp_dir};
+
+#[test]
+fn exit_status_tells_clean_invalid_and_truncated_runs_apart() {
+    let dir = temp_dir("exit-status");
+    std::fs::write(dir.join("clean.csv"), "a,b\nc,d\n").unwrap();
+    std::fs::write(dir.join("data.csv"), "a,b\nc@,d\ne,f,g\n").unwrap();
+
+    let status = |args: &[&str]| {
+        let output = csv_validate(&dir, args);
+        (output.status.code(), String::from_utf8(output.stdout).unwrap().lines().count())
+    };
+    assert_eq!(status(&["clean.csv", "--field-count", "2"]), (Some(0), 0));
+    // issues that aren't errors don't fail the run
+    assert_eq!(status(&["data.csv", "--field-count", "2", "--severity", "warning"]), (Some(0), 1));
+    assert_eq!(status(&["data.csv", "--field-count", "2", "--illegal-chars", "@"]), (Some(2), 2));
+    // stopped early, whatever was found
+    assert_eq!(status(&["data.csv", "--field-count", "2", "--illegal-chars", "@", "--fail-fast"]), (Some(3), 1));
+    assert_eq!(status(&["data.csv", "--field-count", "2", "--severity", "warning", "--max-issues", "1"]), (Some(3), 1));
+    // a run that fails
+    assert_eq!(status(&["missing.csv", "--field-count", "2"]), (Some(1), 0));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,14 @@
+use std::process::Command;
+
+/// The shipped schema is the one the config types generate.
+#[test]
+fn shipped_schema_is_up_to_date() {
+    let output = Command::new(env!("CARGO_BIN_EXE_csv-validate")).arg("schema").output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let shipped = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/config.schema.json")).unwrap();
+    assert!(
+        String::from_utf8(output.stdout).unwrap() == shipped,
+        "config.schema.json is out of date, regenerate it with: cargo run -p csv-validate -- schema > crates/csv-validate/config.schema.json"
+    );
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use common::{csv_validate, temp_dir};
+
+#[test]
+fn table_schema_checks_header_types_and_keys() {
+    let dir = temp_dir("table-schema");
+    std::fs::write(
+        dir.join("schema.json"),
+        r#"{
+          "fields": [
+            {"name": "id", "type": "integer"},
+            {"name": "country", "constraints": {"enum": ["FR", "DE", "XX"]}},
+            {"name": "email", "format": "email"}
+          ],
+          "primaryKey": "id",
+          "foreignKeys": [{"fields": "country", "reference": {"resource": "countries.csv", "fields": "code"}}],
+          "missingValues": ["", "NA"]
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("countries.csv"), "code,label\nFR,France\nDE,Germany\n").unwrap();
+    std::fs::write(dir.join("data.csv"), "id,country,mail\n1,FR,a\n1,XX,b\nNA,DE,c\n").unwrap();
+
+    let output = csv_validate(&dir, &["--table-schema", "schema.json", "data.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "warning: schema.json: field 'email': format 'email' isn't supported, its values are read as strings\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[columns] error HD001 Line 1, Position Some(11): Header column 'mail' should be 'email'\n\
+         [columns] error CV008 Line 3, Position Some(2): Column(s) country: 'XX' is not in countries.csv (code)\n\
+         [columns] error CV003 Line 3, Position Some(0): Column(s) id: '1' is not unique, also on line 2\n\
+         [columns] error CV002 Line 4, Position Some(0): Column 'id' is required, but has no value\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn foreign_key_resources_are_named_in_the_data_package() {
+    let dir = temp_dir("data-package");
+    std::fs::create_dir_all(dir.join("ref")).unwrap();
+    std::fs::write(
+        dir.join("datapackage.json"),
+        r#"{"resources": [{"name": "countries", "path": "ref/countries.csv"}, {"name": "remote", "path": "https://example.com/r.csv"}]}"#,
+    )
+    .unwrap();
+    let schema = |resource: &str| {
+        format!(
+            r#"{{"fields": [{{"name": "country"}}], "foreignKeys": [{{"fields": "country", "reference": {{"resource": "{}", "fields": "code"}}}}]}}"#,
+            resource
+        )
+    };
+    std::fs::write(dir.join("ref/countries.csv"), "code\nFR\n").unwrap();
+    std::fs::write(dir.join("data.csv"), "country\nFR\nXX\n").unwrap();
+
+    std::fs::write(dir.join("schema.json"), schema("countries")).unwrap();
+    let output = csv_validate(&dir, &["--table-schema", "schema.json", "data.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[columns] error CV008 Line 3, Position Some(0): Column(s) country: 'XX' is not in ref/countries.csv (code)\n"
+    );
+
+    for (resource, error) in [
+        ("regions", "the foreign key resource 'regions' isn't in datapackage.json, it has: countries, remote"),
+        ("remote", "the foreign key resource 'remote' of datapackage.json isn't one local file"),
+    ] {
+        std::fs::write(dir.join("schema.json"), schema(resource)).unwrap();
+        let output = csv_validate(&dir, &["--table-schema", "schema.json", "data.csv"]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains(error), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    // without a data package, a resource is a path
+    std::fs::remove_file(dir.join("datapackage.json")).unwrap();
+    std::fs::write(dir.join("schema.json"), schema("countries")).unwrap();
+    let output = csv_validate(&dir, &["--table-schema", "schema.json", "data.csv"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("the foreign key resource 'countries' is no file next to the table schema, and there's no datapackage.json to name it"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,12 +1,12 @@
 use csv_validator_core::{
-    ValidatorSpec, ValidationOptions, validate_file, ValidationIssue,
+    Severity, ValidatorSpec, ValidationOptions, validate_file,
 };
 use std::sync::Arc;
 
-fn main() -> std::io::Result<()> {
+fn main() -> csv_validator_core::Result<()> {
     let file_path = "examples/output_2g.csv";
 
-    /// Define validator specs
+    // Define validator specs
     let specs = vec![
         ValidatorSpec::new_illegal_chars(vec![
             r#"137\n"#.to_string(),
@@ -18,30 +18,34 @@ fn main() -> std::io::Result<()> {
         ]),
         ValidatorSpec::new_field_count(50),
         ValidatorSpec::LineLength {
+            id: None,
             enabled: true,
             max_length: 1024,
+            severity: Severity::Error,
+            common: None,
         },
     ];
 
-    /// Build validators from specs
+    // Build validators from specs
     let validators = Arc::new(
         specs
             .into_iter()
             .map(|spec| spec.into_validator(b';'))
-            .collect::<Vec<_>>(),
+            .collect::<csv_validator_core::Result<Vec<_>>>()?,
     );
 
-    /// Setup validation options
+    // Setup validation options
     let options = ValidationOptions {
         threads: 8,
         batch_size: 100_000,
         buffer_size: 8 * 1024 * 1024,
+        ..Default::default()
     };
 
-    /// Run validation
-    let issues = validate_file(file_path, validators, options)?;
+    // Run validation
+    let issues = validate_file(file_path, validators, options)?.issues;
 
-    /// Print output
+    // Print output
     for issue in issues {
         println!(
             "[{}] Line {}, Position {:?}: {}",
@@ -0,0 +1,620 @@
+use crate::validators::{split_terminator, trim_quotes};
+use crate::{Dialect, Error, IssueCode, Result, ScannedRecord, Severity, StructuralIndexer, UniqueValues, ValidationIssue, Validator};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The type of a column's values, as in Frictionless Table Schema and CSVW, written in their default formats:
+/// ie. `2024-02-29` for a date, `true`, `false`, `1` or `0` for a boolean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    /// any text
+    #[default]
+    String,
+    Integer,
+    /// a decimal number, with an optional exponent, or `NaN`, `INF` and `-INF`
+    Number,
+    Boolean,
+    /// `YYYY-MM-DD`
+    Date,
+    /// `hh:mm:ss`, with optional fractional seconds
+    Time,
+    /// `YYYY-MM-DDThh:mm:ss`, with optional fractional seconds and time zone (`Z` or `+hh:mm`)
+    Datetime,
+    /// `YYYY`
+    Year,
+    /// `YYYY-MM`
+    Yearmonth,
+}
+
+impl ColumnType {
+    pub const ALL: [ColumnType; 9] = [
+        ColumnType::String,
+        ColumnType::Integer,
+        ColumnType::Number,
+        ColumnType::Boolean,
+        ColumnType::Date,
+        ColumnType::Time,
+        ColumnType::Datetime,
+        ColumnType::Year,
+        ColumnType::Yearmonth,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColumnType::String => "string",
+            ColumnType::Integer => "integer",
+            ColumnType::Number => "number",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+            ColumnType::Time => "time",
+            ColumnType::Datetime => "datetime",
+            ColumnType::Year => "year",
+            ColumnType::Yearmonth => "yearmonth",
+        }
+    }
+
+    /// `value` is a value of this type.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            ColumnType::String => true,
+            ColumnType::Integer => parse_integer(value).is_some(),
+            ColumnType::Number => parse_number(value).is_some(),
+            ColumnType::Boolean => matches!(value, "true" | "True" | "TRUE" | "1" | "false" | "False" | "FALSE" | "0"),
+            ColumnType::Date => is_date(value),
+            ColumnType::Time => is_time(value),
+            ColumnType::Datetime => value.split_once('T').is_some_and(|(date, time)| is_date(date) && is_zoned_time(time)),
+            ColumnType::Year => value.len() == 4 && digits(value).is_some(),
+            ColumnType::Yearmonth => value.split_once('-').is_some_and(|(year, month)| {
+                year.len() == 4 && digits(year).is_some() && month.len() == 2 && digits(month).is_some_and(|m| (1..=12).contains(&m))
+            }),
+        }
+    }
+
+    /// Numbers compare by value, dates and times in their default format compare as text.
+    fn compare(&self, a: &str, b: &str) -> Option<std::cmp::Ordering> {
+        match self {
+            ColumnType::Integer => Some(parse_integer(a)?.cmp(&parse_integer(b)?)),
+            ColumnType::Number => parse_number(a)?.partial_cmp(&parse_number(b)?),
+            ColumnType::Date | ColumnType::Time | ColumnType::Datetime | ColumnType::Year | ColumnType::Yearmonth => Some(a.cmp(b)),
+            ColumnType::String | ColumnType::Boolean => None,
+        }
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match ColumnType::ALL.into_iter().find(|datatype| datatype.as_str() == s) {
+            Some(datatype) => Ok(datatype),
+            None => {
+                let types: Vec<_> = ColumnType::ALL.iter().map(ColumnType::as_str).collect();
+                Err(format!("invalid column type '{}', expected one of: {}", s, types.join(", ")))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn parse_integer(value: &str) -> Option<i128> {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    digits(unsigned)?;
+    value.parse().ok()
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    match value {
+        "NaN" => Some(f64::NAN),
+        "INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        // Rust also reads `inf` and `infinity`, which aren't numbers here
+        _ if value.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E')) => value.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The value of a non-empty run of ASCII digits.
+fn digits(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok().or(Some(u32::MAX))
+}
+
+fn is_date(value: &str) -> bool {
+    let mut parts = value.split('-');
+    let (Some(year), Some(month), Some(day), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let (Some(y), Some(m), Some(d)) = (digits(year), digits(month), digits(day)) else { return false };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let leap = y % 4 == 0 && (y % 100 != 0 || y % 400 == 0);
+    let days = match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&d)
+}
+
+fn is_time(value: &str) -> bool {
+    let (time, fraction) = value.split_once('.').unwrap_or((value, "0"));
+    let mut parts = time.split(':');
+    let (Some(h), Some(m), Some(s), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    [h, m, s].iter().all(|part| part.len() == 2)
+        && digits(h).is_some_and(|h| h < 24)
+        && digits(m).is_some_and(|m| m < 60)
+        && digits(s).is_some_and(|s| s < 60)
+        && digits(fraction).is_some()
+}
+
+/// A time followed by an optional `Z` or `+hh:mm` offset.
+fn is_zoned_time(value: &str) -> bool {
+    if let Some(time) = value.strip_suffix('Z') {
+        return is_time(time);
+    }
+    match value.rfind(['+', '-']) {
+        Some(at) => {
+            let offset = &value[at + 1..];
+            is_time(&value[..at]) && offset.len() == 5 && is_time(&format!("{}:00", offset))
+        }
+        None => is_time(value),
+    }
+}
+
+/// A column definition: the column's name and the constraints on its values.
+/// Values that are missing (see `missing_values`) are only checked against `required`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Column {
+    /// the name in the issues, and in the header unless `titles` are given
+    pub name: String,
+    /// the names the header can have for this column, instead of `name`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub titles: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub datatype: ColumnType,
+    /// the value can't be missing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    /// no two records have the same value
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+    /// the only values allowed
+    #[serde(default, rename = "enum", skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<String>>,
+    /// a regular expression the whole value matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// the smallest value allowed, for numbers, dates and times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<String>,
+    /// the largest value allowed, for numbers, dates and times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<String>,
+    /// the minimum length of the value, in characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// the maximum length of the value, in characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    /// the values that stand for no value in this column, instead of the validator's `missing_values`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_values: Option<Vec<String>>,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, datatype: ColumnType) -> Self {
+        Self { name: name.into(), datatype, ..Default::default() }
+    }
+}
+
+/// Values of `columns` that must appear in `reference_columns` of another CSV file, ie. a country code in a list
+/// of countries. The file has a header, and the dialect of the validated file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    /// path of the CSV file holding the referenced values
+    pub reference: String,
+    /// the columns of the referenced file, by name: one per column
+    pub reference_columns: Vec<String>,
+}
+
+#[derive(Clone)]
+struct ReferenceKey {
+    columns: Vec<usize>,
+    values: Arc<HashSet<Vec<String>>>,
+    /// the referenced file and columns, for the issue messages
+    reference: String,
+}
+
+/// Checks every field against its column definition, the columns in order: its type, then its constraints.
+/// With a header, the header line is checked against the column names, or titles, instead.
+///
+/// The engine checks the unique keys across all records, in line order: a duplicate is reported on the later line,
+/// and the issue names the first one.
+#[derive(Clone)]
+pub struct ColumnsValidator {
+    columns: Vec<Column>,
+    /// per column, the compiled `pattern`
+    patterns: Vec<Option<Regex>>,
+    missing_values: Vec<String>,
+    /// the columns of each unique key
+    unique_keys: Vec<Vec<usize>>,
+    foreign_keys: Vec<ReferenceKey>,
+    header: bool,
+    skip_rows: usize,
+    delimiter: u8,
+    quote: u8,
+    indexer: StructuralIndexer,
+    severity: Severity,
+}
+
+impl ColumnsValidator {
+    /// Fails on patterns that aren't regular expressions, and on minimums and maximums that aren't values of their column.
+    pub fn new(columns: Vec<Column>, delimiter: u8) -> Result<Self> {
+        let mut patterns = Vec::with_capacity(columns.len());
+        for column in &columns {
+            let invalid = |what: &str| Error::config(format!("column '{}': {}", column.name, what));
+            patterns.push(match &column.pattern {
+                // patterns match the whole value
+                Some(pattern) => Some(Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| invalid(&format!("invalid pattern: {}", e)))?),
+                None => None,
+            });
+            for bound in column.minimum.iter().chain(&column.maximum) {
+                if column.datatype.compare(bound, bound).is_none() {
+                    return Err(invalid(&format!("minimum and maximum are for numbers, dates and times, not {} values", column.datatype)));
+                }
+                if !column.datatype.accepts(bound) {
+                    return Err(invalid(&format!("'{}' is not a {} value", bound, column.datatype)));
+                }
+            }
+        }
+
+        let mut validator = Self {
+            columns,
+            patterns,
+            missing_values: vec![String::new()],
+            unique_keys: Vec::new(),
+            foreign_keys: Vec::new(),
+            header: false,
+            skip_rows: 0,
+            delimiter,
+            quote: b'"',
+            indexer: StructuralIndexer::new(delimiter, b'"'),
+            severity: Severity::Error,
+        };
+        let unique_columns: Vec<_> = validator.columns.iter().filter(|c| c.unique).map(|c| vec![c.name.clone()]).collect();
+        for key in unique_columns {
+            validator = validator.with_unique_key(&key)?;
+        }
+        Ok(validator)
+    }
+
+    /// The first line after the skipped ones holds the column names.
+    pub fn with_header(self, header: bool) -> Self {
+        Self { header, ..self }
+    }
+
+    /// Lines before the header, which the engine doesn't validate.
+    pub fn with_skip_rows(self, skip_rows: usize) -> Self {
+        Self { skip_rows, ..self }
+    }
+
+    /// Delimiters between this quote char don't count, `"` by default.
+    pub fn with_quote(self, quote: u8) -> Self {
+        Self { quote, indexer: StructuralIndexer::new(self.delimiter, quote), ..self }
+    }
+
+    /// The values that stand for no value, `""` by default.
+    pub fn with_missing_values(self, missing_values: Vec<String>) -> Self {
+        Self { missing_values, ..self }
+    }
+
+    /// No two records have the same values in these columns together, ie. a primary key.
+    /// Records with a missing value in one of them are left out.
+    pub fn with_unique_key(mut self, columns: &[String]) -> Result<Self> {
+        let columns = self.column_indexes(columns)?;
+        self.unique_keys.push(columns);
+        Ok(self)
+    }
+
+    /// The values of `columns` together are one of `values`. Records with a missing value in one of them are left out.
+    pub fn with_foreign_key(mut self, columns: &[String], values: HashSet<Vec<String>>, reference: impl Into<String>) -> Result<Self> {
+        let columns = self.column_indexes(columns)?;
+        self.foreign_keys.push(ReferenceKey { columns, values: Arc::new(values), reference: reference.into() });
+        Ok(self)
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    fn column_indexes(&self, names: &[String]) -> Result<Vec<usize>> {
+        if names.is_empty() {
+            return Err(Error::config("a key needs at least one column"));
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.columns
+                    .iter()
+                    .position(|column| column.name == *name)
+                    .ok_or_else(|| Error::config(format!("no column '{}' for the key", name)))
+            })
+            .collect()
+    }
+
+    fn issue(&self, code: IssueCode, line_number: usize, message: String) -> ValidationIssue {
+        ValidationIssue::new(self.name(), code, line_number, message).with_severity(self.severity)
+    }
+
+    fn is_header(&self, line_number: usize) -> bool {
+        self.header && line_number == self.skip_rows + 1
+    }
+
+    /// The value of the column at `i`, None for missing values, and fields beyond the end of the record.
+    fn value<'a>(&self, line: &'a [u8], fields: &[Range<usize>], i: usize) -> Option<Cow<'a, str>> {
+        let value = fields.get(i).map(|field| unquote(field_in(line, field), self.quote));
+        let missing_values = self.columns[i].missing_values.as_ref().unwrap_or(&self.missing_values);
+        value.filter(|value| !missing_values.iter().any(|missing| missing == value))
+    }
+
+    fn key_names(&self, columns: &[usize]) -> String {
+        columns.iter().map(|&c| self.columns[c].name.as_str()).collect::<Vec<_>>().join(", ")
+    }
+
+    fn check_header(&self, line: &[u8], fields: &[Range<usize>], line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        for (column, field) in self.columns.iter().zip(fields) {
+            let found = String::from_utf8_lossy(trim_quotes(field_in(line, field), self.quote));
+            if !column.titles.iter().chain([&column.name]).any(|title| *title == found) {
+                let expected = column.titles.first().unwrap_or(&column.name);
+                let message = format!("Header column '{}' should be '{}'", found, expected);
+                issues.push(
+                    self.issue(IssueCode::HEADER_MISMATCH, line_number, message)
+                        .at(field.start)
+                        .with_param("column", column.name.as_str())
+                        .with_param("found", found.into_owned()),
+                );
+            }
+        }
+        if let Some(missing) = self.columns.get(fields.len()..).filter(|missing| !missing.is_empty()) {
+            let names: Vec<_> = missing.iter().map(|column| column.name.as_str()).collect();
+            let message = format!("Header is missing column(s) {}", names.join(", "));
+            issues.push(self.issue(IssueCode::HEADER_MISMATCH, line_number, message).with_param("column", names.join(", ")));
+        }
+    }
+
+    fn check(&self, line: &[u8], fields: &[Range<usize>], line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        // the `\r` of a CRLF line end isn't part of the last field
+        let line = split_terminator(line).0;
+        if self.is_header(line_number) {
+            return self.check_header(line, fields, line_number, issues);
+        }
+
+        let values: Vec<Option<Cow<str>>> = (0..self.columns.len()).map(|i| self.value(line, fields, i)).collect();
+
+        for (i, (column, value)) in self.columns.iter().zip(&values).enumerate() {
+            let at = fields.get(i).map(|field| field.start);
+            let located = |issue: ValidationIssue| {
+                let issue = issue.with_param("column", column.name.as_str());
+                match at {
+                    Some(at) => issue.at(at),
+                    None => issue,
+                }
+            };
+            let Some(value) = value.as_deref() else {
+                if column.required {
+                    let message = format!("Column '{}' is required, but has no value", column.name);
+                    issues.push(located(self.issue(IssueCode::REQUIRED_VALUE_MISSING, line_number, message)));
+                }
+                continue;
+            };
+            let invalid = |code: IssueCode, message: String| located(self.issue(code, line_number, message).with_param("found", value));
+
+            if !column.datatype.accepts(value) {
+                let message = format!("Column '{}': '{}' is not a valid {}", column.name, value, column.datatype);
+                issues.push(invalid(IssueCode::TYPE_MISMATCH, message).with_param("type", column.datatype.as_str()));
+                continue;
+            }
+            if let Some(allowed) = &column.allowed
+                && !allowed.iter().any(|allowed| allowed == value)
+            {
+                let message = format!("Column '{}': '{}' is not one of {}", column.name, value, allowed.join(", "));
+                issues.push(invalid(IssueCode::NOT_IN_ENUM, message));
+            }
+            if let (Some(regex), Some(pattern)) = (&self.patterns[i], &column.pattern)
+                && !regex.is_match(value)
+            {
+                let message = format!("Column '{}': '{}' doesn't match the pattern {}", column.name, value, pattern);
+                issues.push(invalid(IssueCode::PATTERN_MISMATCH, message));
+            }
+            if let Some(minimum) = &column.minimum
+                && column.datatype.compare(value, minimum) == Some(std::cmp::Ordering::Less)
+            {
+                let message = format!("Column '{}': {} is below the minimum {}", column.name, value, minimum);
+                issues.push(invalid(IssueCode::OUT_OF_RANGE, message).with_param("minimum", minimum.as_str()));
+            }
+            if let Some(maximum) = &column.maximum
+                && column.datatype.compare(value, maximum) == Some(std::cmp::Ordering::Greater)
+            {
+                let message = format!("Column '{}': {} is above the maximum {}", column.name, value, maximum);
+                issues.push(invalid(IssueCode::OUT_OF_RANGE, message).with_param("maximum", maximum.as_str()));
+            }
+            let length = value.chars().count();
+            if column.min_length.is_some_and(|min| length < min) || column.max_length.is_some_and(|max| length > max) {
+                let range = match (column.min_length, column.max_length) {
+                    (Some(min), Some(max)) => format!("between {} and {}", min, max),
+                    (Some(min), None) => format!("at least {}", min),
+                    (None, _) => format!("at most {}", column.max_length.unwrap_or_default()),
+                };
+                let message = format!("Column '{}': '{}' is {} characters long, expected {}", column.name, value, length, range);
+                issues.push(invalid(IssueCode::LENGTH_OUT_OF_RANGE, message).with_param("length", length));
+            }
+        }
+
+        self.check_keys(&values, fields, line_number, issues);
+    }
+
+    fn check_keys(&self, values: &[Option<Cow<str>>], fields: &[Range<usize>], line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        for foreign in &self.foreign_keys {
+            let Some(key) = foreign.columns.iter().map(|&c| values[c].as_deref().map(str::to_string)).collect::<Option<Vec<_>>>() else {
+                continue;
+            };
+            if !foreign.values.contains(&key) {
+                let names = self.key_names(&foreign.columns);
+                let message = format!("Column(s) {}: '{}' is not in {}", names, key.join(", "), foreign.reference);
+                let issue = self
+                    .issue(IssueCode::FOREIGN_KEY_VIOLATION, line_number, message)
+                    .with_param("reference", foreign.reference.as_str())
+                    .with_param("column", names)
+                    .with_param("found", key.join(", "));
+                issues.push(match fields.get(foreign.columns[0]) {
+                    Some(field) => issue.at(field.start),
+                    None => issue,
+                });
+            }
+        }
+    }
+}
+
+/// The field in `line`, a line without the `\r` its fields were split with.
+fn field_in<'a>(line: &'a [u8], field: &Range<usize>) -> &'a [u8] {
+    &line[field.start.min(line.len())..field.end.min(line.len())]
+}
+
+/// The field without its quotes, doubled quotes inside undoubled.
+fn unquote(field: &[u8], quote: u8) -> Cow<'_, str> {
+    let inner = trim_quotes(field, quote);
+    let text = String::from_utf8_lossy(inner);
+    if inner.len() < field.len() && inner.contains(&quote) {
+        let quote = (quote as char).to_string();
+        return Cow::Owned(text.replace(&quote.repeat(2), &quote));
+    }
+    text
+}
+
+impl Validator for ColumnsValidator {
+    fn validate(&self, line: &[u8], line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        self.check(line, &self.indexer.fields(line), line_number, issues);
+    }
+
+    fn validate_record(&self, record: &ScannedRecord, line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        match record.fields {
+            Some(fields) => self.check(record.line, fields, line_number, issues),
+            None => self.validate(record.line, line_number, issues),
+        }
+    }
+
+    fn field_delimiter(&self) -> Option<u8> {
+        Some(self.delimiter)
+    }
+
+    fn has_unique_keys(&self) -> bool {
+        !self.unique_keys.is_empty()
+    }
+
+    fn unique_values(&self, record: &ScannedRecord, line_number: usize, values: &mut Vec<UniqueValues>) {
+        if self.unique_keys.is_empty() || self.is_header(line_number) {
+            return;
+        }
+        let split;
+        let fields = match record.fields {
+            Some(fields) => fields,
+            None => {
+                split = self.indexer.fields(record.line);
+                &split
+            }
+        };
+        let line = split_terminator(record.line).0;
+        for (key, columns) in self.unique_keys.iter().enumerate() {
+            let found = columns.iter().map(|&c| self.value(line, fields, c).map(Cow::into_owned)).collect();
+            if let Some(found) = found {
+                values.push(UniqueValues { key, values: found, position: fields.get(columns[0]).map(|field| field.start) });
+            }
+        }
+    }
+
+    fn duplicate_issue(&self, values: &UniqueValues, line_number: usize, first_line: usize) -> Option<ValidationIssue> {
+        let names = self.key_names(self.unique_keys.get(values.key)?);
+        let found = values.values.join(", ");
+        let message = format!("Column(s) {}: '{}' is not unique, also on line {}", names, found, first_line);
+        let issue = self
+            .issue(IssueCode::NOT_UNIQUE, line_number, message)
+            .with_param("line", first_line)
+            .with_param("column", names)
+            .with_param("found", found);
+        Some(match values.position {
+            Some(position) => issue.at(position),
+            None => issue,
+        })
+    }
+
+    fn field_quote(&self) -> u8 {
+        self.quote
+    }
+
+    fn name(&self) -> &'static str {
+        "columns"
+    }
+
+    fn clone_box(&self) -> Box<dyn Validator> {
+        Box::new(self.clone())
+    }
+}
+
+/// The values of `columns` in the CSV file at `path`, for `ColumnsValidator::with_foreign_key`.
+/// The file is in `dialect`, with a header.
+pub fn read_reference(path: &str, columns: &[String], dialect: &Dialect) -> Result<HashSet<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(dialect.separator)
+        .quote(dialect.quote_char)
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| csv_error(path, e))?;
+    let mut records = reader.records().skip(dialect.skip_rows);
+    let header = records.next().transpose().map_err(|e| csv_error(path, e))?.unwrap_or_default();
+    let indexes = columns
+        .iter()
+        .map(|name| {
+            header.iter().position(|found| found == name).ok_or_else(|| Error::config(format!("no column '{}' in {}", name, path)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut values = HashSet::new();
+    for record in records {
+        let record = record.map_err(|e| csv_error(path, e))?;
+        values.insert(indexes.iter().map(|&i| record.get(i).unwrap_or_default().to_string()).collect());
+    }
+    Ok(values)
+}
+
+fn csv_error(path: &str, error: csv::Error) -> Error {
+    match error.into_kind() {
+        csv::ErrorKind::Io(e) => Error::Io(e),
+        kind => Error::config(format!("can't read {}: {:?}", path, kind)),
+    }
+}
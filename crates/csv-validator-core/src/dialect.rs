@@ -0,0 +1,121 @@
+use crate::{Error, Result, ValidatorSpec};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// The `common` block of a config file, at the top level or on one validator: settings left out are inherited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct CommonSettings {
+    /// the field separator, an ASCII character: `,` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<char>,
+    /// separators and newlines between quotes are part of the field: `"` by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote_char: Option<char>,
+    /// the first line is a header: false by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_header: Option<bool>,
+    /// lines before the header, or the first record, that aren't validated: 0 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_rows: Option<usize>,
+}
+
+impl CommonSettings {
+    /// Field by field: the settings of `self`, those of `fallback` where `self` has none.
+    pub fn or(&self, fallback: &CommonSettings) -> CommonSettings {
+        CommonSettings {
+            separator: self.separator.or(fallback.separator),
+            quote_char: self.quote_char.or(fallback.quote_char),
+            has_header: self.has_header.or(fallback.has_header),
+            skip_rows: self.skip_rows.or(fallback.skip_rows),
+        }
+    }
+}
+
+/// How the records of a stream are split into fields, resolved from the `common` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dialect {
+    pub separator: u8,
+    pub quote_char: u8,
+    pub has_header: bool,
+    pub skip_rows: usize,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self { separator: b',', quote_char: b'"', has_header: false, skip_rows: 0 }
+    }
+}
+
+impl Dialect {
+    /// Each setting comes from, by precedence: `overrides` (ie. command line flags), the validator's own `common`,
+    /// the top-level `common`, the default.
+    ///
+    /// There is one reader for all validators, so the enabled validators have to agree on every setting:
+    /// ie. a `;` separator on one and `,` on another is a configuration error listing the conflicts.
+    pub fn resolve(overrides: &CommonSettings, top: &CommonSettings, specs: &[ValidatorSpec]) -> Result<Dialect> {
+        let inherited = overrides.or(top);
+        let mut resolved: Vec<(String, CommonSettings)> = specs
+            .iter()
+            .enumerate()
+            .filter(|(_, spec)| spec.is_enabled())
+            .map(|(i, spec)| {
+                let settings = match spec.common() {
+                    Some(own) => overrides.or(own).or(top),
+                    None => inherited.clone(),
+                };
+                (format!("{} (validator {})", spec.type_name(), i + 1), settings)
+            })
+            .collect();
+        if resolved.is_empty() {
+            resolved.push(("common".to_string(), inherited));
+        }
+
+        let default = Dialect::default();
+        let mut conflicts = Vec::new();
+        let separator = agree("separator", &resolved, |s| s.separator.unwrap_or(default.separator as char), &mut conflicts);
+        let quote_char = agree("quote_char", &resolved, |s| s.quote_char.unwrap_or(default.quote_char as char), &mut conflicts);
+        let has_header = agree("has_header", &resolved, |s| s.has_header.unwrap_or(default.has_header), &mut conflicts);
+        let skip_rows = agree("skip_rows", &resolved, |s| s.skip_rows.unwrap_or(default.skip_rows), &mut conflicts);
+        if !conflicts.is_empty() {
+            return Err(Error::config(format!(
+                "validators read the same stream, their common settings conflict: {}",
+                conflicts.join("; ")
+            )));
+        }
+
+        Ok(Dialect {
+            separator: single_byte("separator", separator)?,
+            quote_char: single_byte("quote_char", quote_char)?,
+            has_header,
+            skip_rows,
+        })
+    }
+}
+
+/// The setting all validators agree on. Otherwise the conflict is added to `conflicts`, and the first one returned.
+fn agree<T: PartialEq + Debug>(
+    setting: &str,
+    resolved: &[(String, CommonSettings)],
+    value: impl Fn(&CommonSettings) -> T,
+    conflicts: &mut Vec<String>,
+) -> T {
+    let mut values = resolved.iter().map(|(by, settings)| (by, value(settings)));
+    let (first_by, first) = match values.next() {
+        Some(first) => first,
+        None => return value(&CommonSettings::default()),
+    };
+    let others: Vec<_> = values.filter(|(_, value)| *value != first).map(|(by, value)| format!("{:?} for {}", value, by)).collect();
+    if !others.is_empty() {
+        conflicts.push(format!("{} {:?} for {}, but {}", setting, first, first_by, others.join(", ")));
+    }
+    first
+}
+
+fn single_byte(setting: &str, c: char) -> Result<u8> {
+    u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| Error::config(format!("{} must be an ASCII character, got {:?}", setting, c)))
+}
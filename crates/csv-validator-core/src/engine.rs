@@ -1,20 +1,87 @@
-use crate::{OptimizedQuoteAwareReader, Validator, ValidationIssue, execute_validators};
-use std::{fs::File, sync::Arc, io::Result};
-use rayon::ThreadPoolBuilder;
-use crossbeam_channel::unbounded;
+use crate::executor::{execute_routing, Abandon, PendingKey, RejectedLine, RoutedBatch, Routing};
+use crate::progress::ProgressTracker;
+use crate::validators::{split_terminator, trim_quotes};
+use crate::{CancellationToken, Error, Result, ChunkedRecordReader, StructuralIndexer, ProgressCallback, FusedScanner, IssueSink, MmapRecordSplitter, RecordBatchReader, Validator, ValidationIssue, ValidationOutcome, ValidationReport};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use std::{fs::File, sync::Arc, io::{BufWriter, Read, Write}};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError};
+
+/// How often the engine checks for cancellation and the timeout while it waits for a batch.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 pub struct ValidationOptions {
+    /// size of the thread pool the validation runs in, unless `thread_pool` is given
     pub threads: usize,
+    /// Run in this pool instead of a pool of its own, ie. to share it with the host application.
+    /// The global rayon pool is never used.
+    pub thread_pool: Option<Arc<ThreadPool>>,
     pub batch_size: usize,
+    /// read size, or chunk size when splitting a memory mapped file
     pub buffer_size: usize,
+    /// `validate_file` only: memory map the file and find the record boundaries on all threads,
+    /// instead of on a single reader thread.
+    pub mmap: bool,
+    /// Batches read ahead but not handed out yet: the reader waits for the validators beyond this,
+    /// so peak memory is roughly max_in_flight × batch_size × average line length.
+    /// Defaults to twice the number of threads.
+    pub max_in_flight: Option<usize>,
+    pub separator: u8,
+    /// separators and newlines between quotes are part of the field
+    pub quote_char: u8,
+    /// the first line is a header: it's copied to the clean output and the rejects
+    pub has_header: bool,
+    /// Lines before the header, or the first record, that aren't CSV: they aren't validated, and are copied
+    /// to the clean or fixed output as they are.
+    pub skip_rows: usize,
+    /// Report issues sorted by line, then by validator declaration order, so every run gives the same report.
+    /// Off, issues come in the order the batches complete, unless there are outputs to write or unique keys to check.
+    pub preserve_order: bool,
+    /// Fix mode: validators run chained and the corrected data is written to this path, in original order.
+    pub fix_output: Option<String>,
+    /// Check mode: records without issues are written to this path, in original order.
+    pub clean_output: Option<String>,
+    /// Records with at least one issue that wasn't fixed are written to this path, in original order,
+    /// with their line number and issue messages as extra columns.
+    pub reject_output: Option<String>,
+    /// Stop once this many issues were reported, ie. to only show the first examples.
+    pub max_issues: Option<usize>,
+    /// Report at most this many issues of each validator, and stop once every validator reached it.
+    pub max_issues_per_validator: Option<usize>,
+    /// Stop at the first issue, ie. to only find out whether a file is bad.
+    pub fail_fast: bool,
+    /// Cancel the validation from another thread: it then fails with `Error::Cancelled`.
+    pub cancellation: Option<CancellationToken>,
+    /// Give up once the validation runs longer than this, with `Error::TimedOut`.
+    pub timeout: Option<Duration>,
+    /// Called with the progress as batches complete, at most every 100ms, and once more at the end.
+    pub on_progress: Option<ProgressCallback>,
 }
 
 impl Default for ValidationOptions {
     fn default() -> Self {
         Self {
             threads: 8,
+            thread_pool: None,
             batch_size: 100_000,
             buffer_size: 8 * 1024 * 1024,
+            mmap: false,
+            max_in_flight: None,
+            separator: b',',
+            quote_char: b'"',
+            has_header: false,
+            skip_rows: 0,
+            preserve_order: true,
+            fix_output: None,
+            clean_output: None,
+            reject_output: None,
+            max_issues: None,
+            max_issues_per_validator: None,
+            fail_fast: false,
+            cancellation: None,
+            timeout: None,
+            on_progress: None,
         }
     }
 }
@@ -23,35 +90,449 @@ pub fn validate_file(
     path: &str,
     validators: Arc<Vec<Box<dyn Validator>>>,
     options: ValidationOptions,
-) -> Result<Vec<ValidationIssue>> {
-    let file = File::open(path)?;
-    let mut reader = OptimizedQuoteAwareReader::new(file, options.buffer_size);
-    let thread_pool = ThreadPoolBuilder::new().num_threads(options.threads).build().unwrap();
+) -> Result<ValidationOutcome> {
+    let mut issues = Vec::new();
+    let report = validate_file_with_sink(path, validators, options, &mut issues)?;
+    Ok(ValidationOutcome { issues, report })
+}
+
+/// Same as `validate_file`, for any input stream (ie. stdin).
+pub fn validate_reader<R: Read>(
+    input: R,
+    validators: Arc<Vec<Box<dyn Validator>>>,
+    options: ValidationOptions,
+) -> Result<ValidationOutcome> {
+    let mut issues = Vec::new();
+    let report = validate_reader_with_sink(input, validators, options, &mut issues)?;
+    Ok(ValidationOutcome { issues, report })
+}
+
+/// Streams the issues into `sink` as batches complete, so memory doesn't grow with the number of issues.
+pub fn validate_file_with_sink(
+    path: &str,
+    validators: Arc<Vec<Box<dyn Validator>>>,
+    options: ValidationOptions,
+    sink: &mut dyn IssueSink,
+) -> Result<ValidationReport> {
+    let pool = thread_pool(&options)?;
+    let total_bytes = Some(std::fs::metadata(path)?.len());
+    if options.mmap {
+        let splitter = MmapRecordSplitter::open_with_quote(path, options.buffer_size, options.quote_char, pool.clone())?;
+        return run(splitter, pool, validators, &options, total_bytes, sink);
+    }
+
+    let reader = ChunkedRecordReader::new(File::open(path)?, options.buffer_size).with_quote(options.quote_char);
+    run(reader, pool, validators, &options, total_bytes, sink)
+}
+
+/// Same as `validate_file_with_sink`, for any input stream (ie. stdin).
+pub fn validate_reader_with_sink<R: Read>(
+    input: R,
+    validators: Arc<Vec<Box<dyn Validator>>>,
+    options: ValidationOptions,
+    sink: &mut dyn IssueSink,
+) -> Result<ValidationReport> {
+    let pool = thread_pool(&options)?;
+    let reader = ChunkedRecordReader::new(input, options.buffer_size).with_quote(options.quote_char);
+    run(reader, pool, validators, &options, None, sink)
+}
+
+fn thread_pool(options: &ValidationOptions) -> Result<Arc<ThreadPool>> {
+    match &options.thread_pool {
+        Some(pool) => Ok(pool.clone()),
+        None => ThreadPoolBuilder::new()
+            .num_threads(options.threads)
+            .build()
+            .map(Arc::new)
+            .map_err(Error::from),
+    }
+}
+
+fn run<B: RecordBatchReader>(
+    mut reader: B,
+    pool: Arc<ThreadPool>,
+    validators: Arc<Vec<Box<dyn Validator>>>,
+    options: &ValidationOptions,
+    total_bytes: Option<u64>,
+    sink: &mut dyn IssueSink,
+) -> Result<ValidationReport> {
+    if options.fix_output.is_some() && options.clean_output.is_some() {
+        return Err(Error::config(
+            "fix_output and clean_output are mutually exclusive: in fix mode the fixed output holds the clean records",
+        ));
+    }
+
+    let started = Instant::now();
+    let writer = create_output(options.fix_output.as_deref().or(options.clean_output.as_deref()))?;
+    let reject_writer = create_output(options.reject_output.as_deref())?;
+    let routing = Routing {
+        fix: options.fix_output.is_some(),
+        keep_lines: writer.is_some() || reject_writer.is_some(),
+        reject_invalid: reject_writer.is_some(),
+        has_header: options.has_header,
+        skip_rows: options.skip_rows,
+        indexer: StructuralIndexer::new(options.separator, options.quote_char),
+    };
+
+    let mut scanner = Arc::new(FusedScanner::new(validators)?);
+    if !options.has_header {
+        scanner = resolve_columns(&scanner, None)?;
+    }
+    let limits = IssueLimits::new(options, scanner.validators());
+
+    // output files are always written in original order, and unique keys are checked in it
+    let ordered = options.preserve_order || routing.keep_lines || scanner.validators().iter().any(|v| v.has_unique_keys());
+    let mut collector = Collector {
+        ordered,
+        pending: BTreeMap::new(),
+        emitted: 0,
+        seen: HashMap::new(),
+        separator: options.separator,
+        quote: options.quote_char,
+        writer,
+        reject_writer,
+        sink,
+        limits,
+        report: ValidationReport::default(),
+        progress: options.on_progress.clone().map(|callback| ProgressTracker::new(callback, total_bytes)),
+    };
+
+    // batches dispatched but not handed out yet: this bounds both the read-ahead and the reordering memory
+    let max_in_flight = options.max_in_flight.unwrap_or(pool.current_num_threads() * 2).max(1);
+
+    // never holds more than max_in_flight batches, the reader waits before dispatching more
     let (sender, receiver) = unbounded();
 
-    let mut line_buf = Vec::with_capacity(1024);
-    let mut batch = Vec::with_capacity(options.batch_size);
-    let mut line_number = 0;
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let abandon = Abandon { requested: options.cancellation.clone(), stopped: CancellationToken::new() };
+    // however run returns (done, limit reached, cancelled, error), the batches still in the pool skip their work
+    let _abandon_on_return = CancelOnDrop(abandon.stopped.clone());
 
-    while let Some(line) = reader.next_logical_line(&mut line_buf)? {
-        line_number += 1;
-        batch.push((line_number, line.to_vec()));
+    let mut batch_index = 0;
 
-        if batch.len() >= options.batch_size {
-            execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
+    'reading: loop {
+        check_interrupted(options, deadline)?;
+        let Some(batch) = reader.next_batch(options.batch_size.max(1))? else { break };
+
+        for (line_number, line) in batch.iter().take_while(|(line_number, _)| !routing.is_record(*line_number)) {
+            if line_number <= options.skip_rows {
+                collector.write_skipped(line)?;
+            } else {
+                collector.write_header(line)?;
+                scanner = resolve_columns(&scanner, Some(line))?;
+            }
         }
-    }
 
-    if !batch.is_empty() {
-        execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
+        execute_routing(&pool, batch_index, batch, scanner.clone(), routing, abandon.clone(), sender.clone());
+        batch_index += 1;
+
+        while batch_index - collector.emitted >= max_in_flight {
+            // the sender is still alive here: a dispatched batch is always sent back
+            let Some(routed) = receive(&receiver, options, deadline)? else { break };
+            collector.collect(routed)?;
+            if collector.limits.reached {
+                break 'reading;
+            }
+        }
+        // hand out whatever completed meanwhile, rather than waiting for the end of the input
+        while let Ok(routed) = receiver.try_recv() {
+            collector.collect(routed)?;
+            if collector.limits.reached {
+                break 'reading;
+            }
+        }
     }
 
     drop(sender);
 
-    let mut issues = Vec::new();
-    for chunk in receiver.iter() {
-        issues.extend(chunk);
+    // once a limit is reached the batches in flight are abandoned rather than waited for
+    if !collector.limits.reached {
+        while let Some(routed) = receive(&receiver, options, deadline)? {
+            collector.collect(routed)?;
+            if collector.limits.reached {
+                break;
+            }
+        }
+    }
+
+    collector.report.truncated = collector.limits.reached || collector.limits.dropped;
+    collector.report.elapsed = started.elapsed();
+    collector.finish()
+}
+
+/// The scanner, over validators that looked up the columns they name in `header` instead, if any did.
+fn resolve_columns(scanner: &Arc<FusedScanner>, header: Option<&[u8]>) -> Result<Arc<FusedScanner>> {
+    let resolved = scanner
+        .validators()
+        .iter()
+        .map(|validator| validator.resolve_columns(header))
+        .collect::<Result<Vec<_>>>()?;
+    if resolved.iter().all(Option::is_none) {
+        return Ok(scanner.clone());
+    }
+    let validators = resolved
+        .into_iter()
+        .zip(scanner.validators())
+        .map(|(resolved, validator)| resolved.unwrap_or_else(|| validator.clone_box()))
+        .collect();
+    Ok(Arc::new(FusedScanner::new(Arc::new(validators))?))
+}
+
+/// Waits for the next batch, None once all were received, checking for cancellation and the timeout meanwhile.
+fn receive(receiver: &Receiver<RoutedBatch>, options: &ValidationOptions, deadline: Option<Instant>) -> Result<Option<RoutedBatch>> {
+    loop {
+        check_interrupted(options, deadline)?;
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(routed) => return Ok(Some(routed)),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(None),
+        }
+    }
+}
+
+fn check_interrupted(options: &ValidationOptions, deadline: Option<Instant>) -> Result<()> {
+    if options.cancellation.as_ref().is_some_and(CancellationToken::is_cancelled) {
+        return Err(Error::Cancelled);
+    }
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return Err(Error::TimedOut(options.timeout.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+/// Cancels the token when dropped.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+fn create_output(path: Option<&str>) -> Result<Option<BufWriter<File>>> {
+    Ok(path.map(|path| File::create(path).map(BufWriter::new)).transpose()?)
+}
+
+/// Gathers the batches as they complete, and hands their issues and lines out,
+/// in batch order when ordered: early batches are parked until it's their turn.
+struct Collector<'s, W: Write> {
+    ordered: bool,
+    pending: BTreeMap<usize, RoutedBatch>,
+    emitted: usize,
+    /// per validator and unique key, the values found so far and the line they were first found on
+    seen: HashMap<(usize, usize), HashMap<Vec<String>, usize>>,
+    separator: u8,
+    quote: u8,
+    writer: Option<W>,
+    reject_writer: Option<W>,
+    sink: &'s mut dyn IssueSink,
+    limits: IssueLimits,
+    report: ValidationReport,
+    progress: Option<ProgressTracker>,
+}
+
+impl<W: Write> Collector<'_, W> {
+    fn collect(&mut self, routed: RoutedBatch) -> Result<()> {
+        if !self.ordered {
+            return self.emit(routed);
+        }
+
+        self.pending.insert(routed.index, routed);
+        while !self.limits.reached {
+            let Some(routed) = self.pending.remove(&self.emitted) else { break };
+            self.emit(routed)?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, mut routed: RoutedBatch) -> Result<()> {
+        self.check_unique(&mut routed);
+        if let Some(writer) = self.writer.as_mut() {
+            for line in &routed.lines {
+                writer.write_all(routed.line(line))?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        if let Some(reject_writer) = self.reject_writer.as_mut() {
+            for reject in &routed.rejects {
+                write_reject(reject_writer, routed.line(&reject.line), reject, self.separator, self.quote)?;
+            }
+        }
+        // issues the limits leave out are neither reported nor counted
+        self.limits.admit(&mut routed.issues);
+        if let Some(progress) = self.progress.as_mut() {
+            progress.advance(routed.data.len(), routed.records, routed.issues.len());
+        }
+        self.count(&routed);
+        if !routed.issues.is_empty() {
+            self.sink.consume(routed.issues)?;
+        }
+        self.emitted += 1;
+        Ok(())
+    }
+
+    /// Checks the unique keys of the batch against those of the batches before it, which were all emitted already.
+    fn check_unique(&mut self, routed: &mut RoutedBatch) {
+        let mut duplicates: Vec<(PendingKey, ValidationIssue)> = Vec::new();
+        for key in std::mem::take(&mut routed.keys) {
+            let seen = self.seen.entry((key.validator, key.values.key)).or_default();
+            let Some(&first_line) = seen.get(&key.values.values) else {
+                seen.insert(key.values.values.clone(), key.line_number);
+                continue;
+            };
+            let validator = &routed.scanner.validators()[key.validator];
+            if let Some(mut issue) = validator.duplicate_issue(&key.values, key.line_number, first_line) {
+                issue.column = key.column;
+                duplicates.push((key, issue));
+            }
+        }
+        if !duplicates.is_empty() {
+            routed.add_duplicates(duplicates, self.reject_writer.is_some());
+        }
+    }
+
+    fn count(&mut self, routed: &RoutedBatch) {
+        let report = &mut self.report;
+        report.records += routed.records as u64;
+        report.bytes += routed.data.len() as u64;
+        report.records_with_issues += routed.records_with_issues as u64;
+        for issue in &routed.issues {
+            *report.issues_per_validator.entry(issue.validator).or_default() += 1;
+            *report.issues_per_severity.entry(issue.severity).or_default() += 1;
+            if let Some(column) = issue.column {
+                *report.issues_per_column.entry(column).or_default() += 1;
+            }
+        }
+    }
+
+    fn write_skipped(&mut self, line: &[u8]) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
     }
 
-    Ok(issues)
+    fn write_header(&mut self, header: &[u8]) -> Result<()> {
+        let (names, terminator) = split_terminator(header);
+        self.report.columns = StructuralIndexer::new(self.separator, self.quote)
+            .fields(names)
+            .into_iter()
+            .map(|name| String::from_utf8_lossy(trim_quotes(&names[name], self.quote)).into_owned())
+            .collect();
+
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(header)?;
+            writer.write_all(b"\n")?;
+        }
+        if let Some(reject_writer) = self.reject_writer.as_mut() {
+            reject_writer.write_all(names)?;
+            for column in [&b"line_number"[..], b"issues"] {
+                reject_writer.write_all(&[self.separator])?;
+                reject_writer.write_all(column)?;
+            }
+            reject_writer.write_all(terminator)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<ValidationReport> {
+        if let Some(progress) = self.progress.as_mut() {
+            progress.finish();
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        if let Some(reject_writer) = self.reject_writer.as_mut() {
+            reject_writer.flush()?;
+        }
+        Ok(self.report)
+    }
+}
+
+/// `max_issues`, `max_issues_per_validator` and `fail_fast`, applied to the issues as they are handed out.
+struct IssueLimits {
+    max_issues: Option<usize>,
+    max_per_validator: Option<usize>,
+    /// validators are told apart by name, like in the report
+    validators: usize,
+    reported: usize,
+    per_validator: HashMap<&'static str, usize>,
+    /// issues were left out of the report
+    dropped: bool,
+    /// time to stop
+    reached: bool,
+}
+
+impl IssueLimits {
+    fn new(options: &ValidationOptions, validators: &[Box<dyn Validator>]) -> Self {
+        let max_issues = match (options.fail_fast, options.max_issues) {
+            (true, max) => Some(max.map_or(1, |max| max.min(1))),
+            (false, max) => max,
+        };
+        Self {
+            max_issues,
+            max_per_validator: options.max_issues_per_validator,
+            validators: validators.iter().map(|v| v.name()).collect::<HashSet<_>>().len(),
+            reported: 0,
+            per_validator: HashMap::new(),
+            dropped: false,
+            reached: false,
+        }
+    }
+
+    /// Drops the issues beyond the limits, and notes when it's time to stop.
+    fn admit(&mut self, issues: &mut Vec<ValidationIssue>) {
+        if self.max_issues.is_none() && self.max_per_validator.is_none() {
+            return;
+        }
+
+        let before = issues.len();
+        issues.retain(|issue| {
+            if self.max_issues.is_some_and(|max| self.reported >= max) {
+                return false;
+            }
+            if let Some(max) = self.max_per_validator {
+                let count = self.per_validator.entry(issue.validator).or_default();
+                if *count >= max {
+                    return false;
+                }
+                *count += 1;
+            }
+            self.reported += 1;
+            true
+        });
+        self.dropped |= issues.len() < before;
+
+        self.reached = self.max_issues.is_some_and(|max| self.reported >= max)
+            || self.max_per_validator.is_some_and(|max| {
+                self.validators > 0
+                    && self.per_validator.len() == self.validators
+                    && self.per_validator.values().all(|&count| count >= max)
+            });
+    }
+}
+
+/// The rejected record as is, followed by its line number and its issue messages as one quoted field.
+fn write_reject<W: Write>(writer: &mut W, line: &[u8], reject: &RejectedLine, separator: u8, quote: u8) -> Result<()> {
+    let (line, terminator) = split_terminator(line);
+    writer.write_all(line)?;
+    writer.write_all(&[separator])?;
+    writer.write_all(reject.line_number.to_string().as_bytes())?;
+    writer.write_all(&[separator, quote])?;
+    for (i, message) in reject.messages.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b" | ")?;
+        }
+        for part in message.as_bytes().split_inclusive(|&b| b == quote) {
+            writer.write_all(part)?;
+            // quotes in the field are doubled
+            if part.last() == Some(&quote) {
+                writer.write_all(&[quote])?;
+            }
+        }
+    }
+    writer.write_all(&[quote])?;
+    writer.write_all(terminator)?;
+    Ok(())
 }
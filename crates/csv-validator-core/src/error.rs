@@ -0,0 +1,33 @@
+use std::io;
+use std::time::Duration;
+
+/// Everything the library can fail with. Bad configuration is an error, never a panic:
+/// release builds abort on panic.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// options or validator settings that can't work, ie. patterns and replacements of different lengths
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    #[error("invalid patterns: {0}")]
+    Pattern(#[from] aho_corasick::BuildError),
+    #[error("failed to start the thread pool: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    /// a text line reader got bytes that aren't UTF-8
+    #[error("line {line} is not valid UTF-8")]
+    Encoding { line: usize },
+    /// the `CancellationToken` was cancelled
+    #[error("validation cancelled")]
+    Cancelled,
+    #[error("validation timed out after {0:?}")]
+    TimedOut(Duration),
+}
+
+impl Error {
+    pub(crate) fn config(message: impl Into<String>) -> Self {
+        Error::Config(message.into())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,67 +1,365 @@
-use std::fs::File;
-use crate::{Validator, ValidationIssue, OptimizedQuoteAwareReader};
+use crate::{
+    CancellationToken, FusedScanner, IssueStatus, RecordBatch, ScanBuffers, ScannedRecord, Severity, StructuralIndexer, UniqueValues,
+    ValidationIssue, ValidationResult, Validator,
+};
+use bytes::Bytes;
 use rayon::prelude::*;
-use crossbeam_channel::{unbounded, Sender};
+use rayon::ThreadPool;
+use crossbeam_channel::Sender;
+use std::borrow::Cow;
+use std::ops::Range;
 use std::sync::Arc;
-use rayon::ThreadPoolBuilder;
-
-pub fn validate_file_with_config(
-    filename: &str,
-    validators: Arc<Vec<Box<dyn Validator>>>,
-    threads: usize,
-    batch_size: usize,
-    buffer_size: usize,
-) -> Result<Vec<ValidationIssue>, Box<dyn std::error::Error>> {
-    let file = File::open(filename)?;
-    let mut reader = OptimizedQuoteAwareReader::new(file, buffer_size);
-    let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
-    let (sender, receiver) = unbounded();
-
-    let mut line_buf = Vec::with_capacity(1024);
-    let mut batch = Vec::with_capacity(batch_size);
-    let mut line_number = 0;
-
-    while let Some(line) = reader.next_logical_line(&mut line_buf)? {
-        line_number += 1;
-        batch.push((line_number, line.to_vec()));
-
-        if batch.len() >= batch_size {
-            execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
-        }
-    }
 
-    if !batch.is_empty() {
-        execute_validators(batch.drain(..).collect(), validators.clone(), sender.clone());
+/// How `execute_routing` treats each line.
+#[derive(Clone, Copy)]
+pub(crate) struct Routing {
+    /// run the validators chained in fix mode
+    pub fix: bool,
+    /// hand the lines back to the engine, to write them to the outputs
+    pub keep_lines: bool,
+    /// lines with an error that wasn't fixed go to the rejects, not only the lines a validator rejected;
+    /// warnings and infos are reported only
+    pub reject_invalid: bool,
+    /// the line after the skipped ones is the header, the engine writes it to the outputs itself
+    pub has_header: bool,
+    /// lines before the header that aren't validated, the engine writes them to the outputs itself
+    pub skip_rows: usize,
+    /// splits the records into fields, to tell which column an issue is in
+    pub indexer: StructuralIndexer,
+}
+
+impl Routing {
+    /// The line is neither skipped nor the header.
+    pub fn is_record(&self, line_number: usize) -> bool {
+        line_number > self.skip_rows + usize::from(self.has_header)
     }
+}
 
-    drop(sender);
+/// Tells the batches still in the pool to give up.
+#[derive(Clone)]
+pub(crate) struct Abandon {
+    /// the caller's token
+    pub requested: Option<CancellationToken>,
+    /// set by the engine once it stops waiting for the batches
+    pub stopped: CancellationToken,
+}
 
-    let mut issues = Vec::new();
-    for chunk in receiver.iter() {
-        issues.extend(chunk);
+impl Abandon {
+    pub fn is_set(&self) -> bool {
+        self.stopped.is_cancelled() || self.requested.as_ref().is_some_and(CancellationToken::is_cancelled)
     }
+}
+
+/// A line to write out: untouched lines point into the batch data, only fixed lines are copies.
+pub(crate) enum OutputLine {
+    Original(Range<usize>),
+    Fixed(Vec<u8>),
+}
+
+/// A record that goes to the rejects, with what was wrong with it.
+pub(crate) struct RejectedLine {
+    pub line_number: usize,
+    pub line: OutputLine,
+    pub messages: Vec<String>,
+}
+
+/// The values of a unique key in a record, which the engine checks against the records before it, in line order.
+pub(crate) struct PendingKey {
+    pub line_number: usize,
+    /// the validator the key is of, by index in the scanner
+    pub validator: usize,
+    pub values: UniqueValues,
+    /// where the issue of a duplicate goes in the batch issues: after the validator's own issues on the line
+    pub at: usize,
+    /// the column at `values.position`
+    pub column: Option<usize>,
+    /// the record has other issues
+    pub had_issues: bool,
+    /// the record as read, to reject it
+    pub record: Range<usize>,
+    /// the record's index in the clean lines, when it went there
+    pub clean: Option<usize>,
+}
 
-    Ok(issues)
+/// Output of one routed batch: the issues found, the clean (possibly fixed) lines and the rejected lines, in input order.
+pub(crate) struct RoutedBatch {
+    pub index: usize,
+    pub data: Bytes,
+    /// the validators the batch ran, which `keys` refer to
+    pub scanner: Arc<FusedScanner>,
+    /// records validated, the header not included
+    pub records: usize,
+    /// records with at least one issue, the header not included
+    pub records_with_issues: usize,
+    pub issues: Vec<ValidationIssue>,
+    pub lines: Vec<OutputLine>,
+    pub rejects: Vec<RejectedLine>,
+    /// the unique key values of the records, in line order
+    pub keys: Vec<PendingKey>,
 }
 
+impl RoutedBatch {
+    pub fn line<'a>(&'a self, line: &'a OutputLine) -> &'a [u8] {
+        match line {
+            OutputLine::Original(range) => &self.data[range.clone()],
+            OutputLine::Fixed(fixed) => fixed,
+        }
+    }
 
+    /// Adds the issues of the records repeating a unique key, in `keys` order: to the issues, the count of records
+    /// with issues, and with `reject_invalid`, to the rejects.
+    pub fn add_duplicates(&mut self, duplicates: Vec<(PendingKey, ValidationIssue)>, reject_invalid: bool) {
+        let mut counted = None;
+        for (key, _) in &duplicates {
+            if !key.had_issues && counted != Some(key.line_number) {
+                self.records_with_issues += 1;
+                counted = Some(key.line_number);
+            }
+        }
+
+        let mut issues = Vec::with_capacity(self.issues.len() + duplicates.len());
+        let mut rest = std::mem::take(&mut self.issues).into_iter();
+        let mut taken = 0;
+        let mut keys = Vec::with_capacity(duplicates.len());
+        for (key, issue) in duplicates {
+            issues.extend(rest.by_ref().take(key.at - taken));
+            taken = key.at;
+            let rejected = is_open_error(&issue);
+            issues.push(issue);
+            keys.push((key, rejected));
+        }
+        issues.extend(rest);
+        self.issues = issues;
+        if !reject_invalid {
+            return;
+        }
+
+        // the rejects list the messages of all the issues of their record
+        let mut moved = Vec::new();
+        for (key, rejected) in keys {
+            let line_number = key.line_number;
+            let start = self.issues.partition_point(|issue| issue.line_number < line_number);
+            let end = self.issues.partition_point(|issue| issue.line_number <= line_number);
+            let messages = self.issues[start..end].iter().map(|issue| issue.message.clone()).collect();
+            let at = self.rejects.partition_point(|reject| reject.line_number < line_number);
+            match (self.rejects.get_mut(at), key.clean) {
+                (Some(reject), _) if reject.line_number == line_number => reject.messages = messages,
+                (_, Some(clean)) if rejected => {
+                    moved.push(clean);
+                    // rejects keep the original record
+                    self.rejects.insert(at, RejectedLine { line_number, line: OutputLine::Original(key.record), messages });
+                }
+                _ => {}
+            }
+        }
+        if !moved.is_empty() {
+            let lines = std::mem::take(&mut self.lines);
+            self.lines = lines.into_iter().enumerate().filter(|(i, _)| moved.binary_search(i).is_err()).map(|(_, line)| line).collect();
+        }
+    }
+}
+
+/// Runs the validators over the records of `batch` on `pool`, scanning each record once for all of them,
+/// and sends the issues found. Unique keys aren't checked: that takes the records of all batches, in order.
 pub fn execute_validators(
-    lines: Vec<(usize, Vec<u8>)>,
-    validators: Arc<Vec<Box<dyn Validator>>>,
+    pool: &ThreadPool,
+    batch: RecordBatch,
+    scanner: Arc<FusedScanner>,
     sender: Sender<Vec<ValidationIssue>>,
 ) {
-    rayon::spawn(move || {
-        let issues_batch: Vec<ValidationIssue> = lines.par_iter()
-            .map(|(line_number, line)| {
+    pool.spawn(move || {
+        let issues_batch: Vec<ValidationIssue> = (0..batch.len()).into_par_iter()
+            .map_init(ScanBuffers::default, |buffers, i| {
                 let mut local_issues = Vec::new();
-                for validator in validators.iter() {
-                    validator.validate(line, *line_number, &mut local_issues);
-                }
+                let line_number = batch.first_line_number + i;
+                scanner.validate(batch.record(i), line_number, buffers, &mut local_issues);
                 local_issues
             })
             .flatten()
             .collect();
 
-        sender.send(issues_batch).expect("Issue sending failed");
+        let _ = sender.send(issues_batch);
     });
-}
\ No newline at end of file
+}
+
+/// Validates a batch and splits it into clean and rejected lines.
+/// Issues come out sorted by line, then by validator declaration order.
+/// In check mode each record is scanned once for all validators.
+/// In fix mode validators run chained in declared order, each one receiving the line as fixed by the previous one,
+/// so each one scans the line as it gets it.
+/// `index` is the batch sequence number, so the engine can write the lines back in original order.
+/// Once `abandon` is set the remaining records are skipped and nothing is sent.
+pub(crate) fn execute_routing(
+    pool: &ThreadPool,
+    index: usize,
+    batch: RecordBatch,
+    scanner: Arc<FusedScanner>,
+    routing: Routing,
+    abandon: Abandon,
+    sender: Sender<RoutedBatch>,
+) {
+    pool.spawn(move || {
+        if abandon.is_set() {
+            return;
+        }
+        let routed: Vec<RoutedRecord> = (0..batch.len()).into_par_iter()
+            .map_init(ScanBuffers::default, |buffers, i| {
+                let line_number = batch.first_line_number + i;
+                let mut routed = RoutedRecord { issues: Vec::new(), fixed: None, rejected: false, keys: Vec::new() };
+                if abandon.is_set() || line_number <= routing.skip_rows {
+                    return routed;
+                }
+                let line = batch.record(i);
+                let mut keys = Vec::new();
+                if !routing.fix {
+                    scanner.validate_each(line, line_number, buffers, &mut routed.issues, |v, record, issues| {
+                        unique_values(&*scanner.validators()[v], v, record, line_number, issues.len(), &mut keys);
+                    });
+                    locate_columns(&routing.indexer, line, &mut routed.issues);
+                    routed.rejected = routing.reject_invalid && routed.issues.iter().any(is_open_error);
+                    routed.keys = pending_keys(&routing.indexer, line, batch.records[i].clone(), line_number, keys);
+                    return routed;
+                }
+
+                let mut result = ValidationResult::new(line);
+                for (v, validator) in scanner.validators().iter().enumerate() {
+                    result = validator.fix(result, line_number, &mut routed.issues);
+                    let record = ScannedRecord { line: &result.line, matches: &[], fields: None };
+                    unique_values(&**validator, v, &record, line_number, routed.issues.len(), &mut keys);
+                    if result.rejected {
+                        break;
+                    }
+                }
+                routed.rejected = result.rejected
+                    || (routing.reject_invalid && routed.issues.iter().any(is_open_error));
+                locate_columns(&routing.indexer, line, &mut routed.issues);
+                routed.keys = pending_keys(&routing.indexer, line, batch.records[i].clone(), line_number, keys);
+                // rejects keep the original record
+                routed.fixed = match result.line {
+                    Cow::Owned(fixed) if !routed.rejected => Some(fixed),
+                    _ => None,
+                };
+                routed
+            })
+            .collect();
+        if abandon.is_set() {
+            return;
+        }
+
+        let mut routed_batch = RoutedBatch {
+            index,
+            data: batch.data.clone(),
+            scanner: scanner.clone(),
+            records: batch.len(),
+            records_with_issues: 0,
+            issues: Vec::new(),
+            lines: Vec::new(),
+            rejects: Vec::new(),
+            keys: Vec::new(),
+        };
+        for (i, RoutedRecord { issues, fixed, rejected, keys }) in routed.into_iter().enumerate() {
+            let line_number = batch.first_line_number + i;
+            if !routing.is_record(line_number) {
+                routed_batch.records -= 1;
+            } else if !issues.is_empty() {
+                routed_batch.records_with_issues += 1;
+            }
+            let line = match fixed {
+                Some(fixed) => OutputLine::Fixed(fixed),
+                None => OutputLine::Original(batch.records[i].clone()),
+            };
+            let mut clean = None;
+            if !routing.keep_lines || !routing.is_record(line_number) {
+                // nothing to write, or the skipped lines and the header, which the engine already wrote
+            } else if rejected {
+                let messages = issues.iter().map(|i| i.message.clone()).collect();
+                routed_batch.rejects.push(RejectedLine { line_number, line, messages });
+            } else {
+                clean = Some(routed_batch.lines.len());
+                routed_batch.lines.push(line);
+            }
+            let (offset, had_issues) = (routed_batch.issues.len(), !issues.is_empty());
+            routed_batch.keys.extend(keys.into_iter().map(|key| PendingKey { at: offset + key.at, had_issues, clean, ..key }));
+            routed_batch.issues.extend(issues);
+        }
+
+        // the engine may have stopped listening, ie. on a sink error: nothing left to do then
+        let _ = sender.send(routed_batch);
+    });
+}
+
+/// What routing found in one record.
+struct RoutedRecord {
+    issues: Vec<ValidationIssue>,
+    /// the line, when the validators changed it
+    fixed: Option<Vec<u8>>,
+    rejected: bool,
+    /// the values of the unique keys, `at` counted in the record's issues
+    keys: Vec<PendingKey>,
+}
+
+/// Adds the unique key values of the record to `keys`, if the validator has unique keys, with the validator's index
+/// and the number of issues of the record so far.
+fn unique_values(
+    validator: &dyn Validator,
+    v: usize,
+    record: &ScannedRecord,
+    line_number: usize,
+    issues: usize,
+    keys: &mut Vec<(usize, usize, UniqueValues)>,
+) {
+    if validator.has_unique_keys() {
+        let mut values = Vec::new();
+        validator.unique_values(record, line_number, &mut values);
+        keys.extend(values.into_iter().map(|values| (v, issues, values)));
+    }
+}
+
+/// The keys of a record, their columns looked up in the original line.
+fn pending_keys(
+    indexer: &StructuralIndexer,
+    line: &[u8],
+    record: Range<usize>,
+    line_number: usize,
+    keys: Vec<(usize, usize, UniqueValues)>,
+) -> Vec<PendingKey> {
+    if keys.is_empty() {
+        return Vec::new();
+    }
+    let fields = indexer.fields(line);
+    keys.into_iter()
+        .map(|(validator, at, values)| PendingKey {
+            line_number,
+            validator,
+            column: values.position.map(|position| column_at(&fields, position)),
+            values,
+            at,
+            had_issues: false,
+            record: record.clone(),
+            clean: None,
+        })
+        .collect()
+}
+
+/// Sets the column of the issues with a position. In fix mode positions are in the line as the validator got it,
+/// the columns are looked up in the original line.
+fn locate_columns(indexer: &StructuralIndexer, line: &[u8], issues: &mut [ValidationIssue]) {
+    if issues.iter().all(|issue| issue.position.is_none()) {
+        return;
+    }
+    let fields = indexer.fields(line);
+    for issue in issues {
+        if let Some(position) = issue.position {
+            issue.column = Some(column_at(&fields, position));
+        }
+    }
+}
+
+/// The field a position in the line is in.
+fn column_at(fields: &[Range<usize>], position: usize) -> usize {
+    fields.partition_point(|field| field.end < position).min(fields.len() - 1)
+}
+
+fn is_open_error(issue: &ValidationIssue) -> bool {
+    issue.severity == Severity::Error && issue.status != IssueStatus::Fixed
+}
@@ -1,41 +1,196 @@
 use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub struct ValidationIssue {
     pub validator: &'static str,
+    /// what kind of issue this is, for tooling to match on instead of the message
+    pub code: IssueCode,
+    pub severity: Severity,
     pub line_number: usize,
     pub position: Option<usize>,
+    /// zero-based field the issue is in, filled in by the engine from `position`
+    pub column: Option<usize>,
     pub message: String,
+    /// the values the message is built from, ie. the expected and found field counts
+    pub params: Vec<(&'static str, IssueParam)>,
+    pub status: IssueStatus,
 }
 
-pub struct ValidationContext {
-    pub issues: Vec<ValidationIssue>,
+impl ValidationIssue {
+    /// An open error without position, `at` and `with_param` add the details.
+    pub fn new(validator: &'static str, code: IssueCode, line_number: usize, message: String) -> Self {
+        Self {
+            validator,
+            code,
+            severity: Severity::Error,
+            line_number,
+            position: None,
+            column: None,
+            message,
+            params: Vec::new(),
+            status: IssueStatus::Open,
+        }
+    }
+
+    pub fn at(self, position: usize) -> Self {
+        Self { position: Some(position), ..self }
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    pub fn with_param(mut self, name: &'static str, value: impl Into<IssueParam>) -> Self {
+        self.params.push((name, value.into()));
+        self
+    }
+
+    pub fn param(&self, name: &str) -> Option<&IssueParam> {
+        self.params.iter().find(|(param, _)| *param == name).map(|(_, value)| value)
+    }
 }
 
-impl ValidationContext {
-    pub fn new() -> Self {
-        Self { issues: Vec::new() }
+/// Stable, machine readable identification of an issue: a short code and a name, ie. `FC001 field_count_mismatch`.
+/// Custom validators define their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IssueCode {
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+impl IssueCode {
+    pub const ILLEGAL_CHARACTER: IssueCode = IssueCode { code: "IC001", name: "illegal_character" };
+    pub const FIELD_COUNT_MISMATCH: IssueCode = IssueCode { code: "FC001", name: "field_count_mismatch" };
+    pub const LINE_TOO_LONG: IssueCode = IssueCode { code: "LL001", name: "line_too_long" };
+    pub const HEADER_MISMATCH: IssueCode = IssueCode { code: "HD001", name: "header_mismatch" };
+    pub const TYPE_MISMATCH: IssueCode = IssueCode { code: "CV001", name: "type_mismatch" };
+    pub const REQUIRED_VALUE_MISSING: IssueCode = IssueCode { code: "CV002", name: "required_value_missing" };
+    pub const NOT_UNIQUE: IssueCode = IssueCode { code: "CV003", name: "not_unique" };
+    pub const NOT_IN_ENUM: IssueCode = IssueCode { code: "CV004", name: "not_in_enum" };
+    pub const PATTERN_MISMATCH: IssueCode = IssueCode { code: "CV005", name: "pattern_mismatch" };
+    pub const OUT_OF_RANGE: IssueCode = IssueCode { code: "CV006", name: "out_of_range" };
+    pub const LENGTH_OUT_OF_RANGE: IssueCode = IssueCode { code: "CV007", name: "length_out_of_range" };
+    pub const FOREIGN_KEY_VIOLATION: IssueCode = IssueCode { code: "CV008", name: "foreign_key_violation" };
+}
+
+impl fmt::Display for IssueCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.name)
     }
+}
+
+/// Value of a structured issue parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueParam {
+    Number(u64),
+    Text(String),
+}
 
-    pub fn add_issue(&mut self, issue: ValidationIssue) {
-        self.issues.push(issue);
+impl From<usize> for IssueParam {
+    fn from(value: usize) -> Self {
+        IssueParam::Number(value as u64)
     }
+}
 
-    pub fn is_valid(&self) -> bool {
-        self.issues.is_empty()
+impl From<String> for IssueParam {
+    fn from(value: String) -> Self {
+        IssueParam::Text(value)
     }
 }
 
-/// Validation result explicitly carries forward the current line
-/// no longer needed since only check-only mode
+impl From<&str> for IssueParam {
+    fn from(value: &str) -> Self {
+        IssueParam::Text(value.to_string())
+    }
+}
+
+impl fmt::Display for IssueParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssueParam::Number(value) => write!(f, "{}", value),
+            IssueParam::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+/// How bad an issue is, set per validator. Only errors send records to the rejects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    /// `error`, `warning` or `info`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "info" => Ok(Severity::Info),
+            _ => Err(format!("invalid severity '{}', expected error, warning or info", s)),
+        }
+    }
+}
+
+/// What happened to the line in fix mode, so auditors can see what changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IssueStatus {
+    /// reported only, the line was passed on as is
+    #[default]
+    Open,
+    /// the validator repaired the line
+    Fixed,
+    /// the line was sent to the reject file
+    Rejected,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Open => "open",
+            IssueStatus::Fixed => "fixed",
+            IssueStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Validation result explicitly carries forward the current line in fix mode:
+/// each validator returns either the original (borrowed) line or the fixed (owned) line
 pub struct ValidationResult<'a> {
-    pub line: Cow<'a, str>,
+    pub line: Cow<'a, [u8]>,
+    /// set by a validator that gives up on the line: it goes to the reject file instead of the fixed output
+    pub rejected: bool,
 }
 
 impl<'a> ValidationResult<'a> {
-    pub fn new(line: &'a str) -> Self {
+    pub fn new(line: &'a [u8]) -> Self {
         Self {
             line: Cow::Borrowed(line),
+            rejected: false,
         }
     }
+
+    /// True once any validator in the chain has rewritten the line.
+    pub fn is_modified(&self) -> bool {
+        matches!(self.line, Cow::Owned(_))
+    }
 }
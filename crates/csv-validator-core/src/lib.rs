@@ -5,11 +5,27 @@ pub mod validators;
 mod executor;
 mod validator_spec;
 mod engine;
+mod sink;
+mod scan;
+mod report;
+mod cancel;
+mod progress;
+mod error;
+mod dialect;
+mod columns;
 
-pub use issue::{ValidationIssue, ValidationResult};
-pub use reader::{BufferedLineReader, FileBufferedReader, MemoryBufferedReader, OptimizedQuoteAwareReader};
-pub use validator::Validator;
-pub use validators::{IllegalCharactersValidator, FieldCountValidator, LineLengthValidator};
+pub use issue::{IssueCode, IssueParam, IssueStatus, Severity, ValidationIssue, ValidationResult};
+pub use reader::{BufferedLineReader, ChunkedRecordReader, FileBufferedReader, MemoryBufferedReader, MmapRecordSplitter, OptimizedQuoteAwareReader, RecordBatch, RecordBatchReader, StructuralIndexer};
+pub use validator::{UniqueValues, Validator};
+pub use validators::{IllegalCharactersValidator, FieldCountValidator, FieldCountRepair, LineLengthValidator, MergeColumn};
 pub use executor::execute_validators;
-pub use validator_spec::ValidatorSpec;
-pub use engine::{ValidationOptions, validate_file};
+pub use validator_spec::{ValidatorSpec, into_validators};
+pub use engine::{ValidationOptions, validate_file, validate_file_with_sink, validate_reader, validate_reader_with_sink};
+pub use sink::IssueSink;
+pub use cancel::CancellationToken;
+pub use progress::{Progress, ProgressCallback};
+pub use report::{ValidationOutcome, ValidationReport};
+pub use error::{Error, Result};
+pub use dialect::{CommonSettings, Dialect};
+pub use columns::{Column, ColumnType, ColumnsValidator, ForeignKey, read_reference};
+pub use scan::{FusedScanner, LiteralMatch, ScanBuffers, ScannedRecord};
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Where a running validation is at, as passed to `ValidationOptions::on_progress`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    /// bytes of the records validated so far
+    pub bytes: u64,
+    /// size of the input, when known: files, not streams
+    pub total_bytes: Option<u64>,
+    /// records validated so far
+    pub records: u64,
+    /// issues reported so far
+    pub issues: u64,
+    pub elapsed: Duration,
+    /// the validation is done, this is the last event
+    pub finished: bool,
+}
+
+impl Progress {
+    /// Bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { self.bytes as f64 / seconds } else { 0.0 }
+    }
+
+    /// Records per second.
+    pub fn records_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { self.records as f64 / seconds } else { 0.0 }
+    }
+
+    /// Share of the input done, between 0 and 1, when its size is known.
+    pub fn fraction(&self) -> Option<f64> {
+        self.total_bytes
+            .map(|total| if total == 0 { 1.0 } else { (self.bytes as f64 / total as f64).min(1.0) })
+    }
+}
+
+/// Receives the progress events, on the thread that runs the validation.
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// How often the engine reports progress at most, the last event is always reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Adds up the batches as they are handed out, and calls the callback now and then.
+pub(crate) struct ProgressTracker {
+    callback: ProgressCallback,
+    started: Instant,
+    reported: Option<Instant>,
+    progress: Progress,
+}
+
+impl ProgressTracker {
+    pub fn new(callback: ProgressCallback, total_bytes: Option<u64>) -> Self {
+        Self {
+            callback,
+            started: Instant::now(),
+            reported: None,
+            progress: Progress { total_bytes, ..Default::default() },
+        }
+    }
+
+    pub fn advance(&mut self, bytes: usize, records: usize, issues: usize) {
+        self.progress.bytes += bytes as u64;
+        self.progress.records += records as u64;
+        self.progress.issues += issues as u64;
+
+        let now = Instant::now();
+        if self.reported.is_none_or(|reported| now - reported >= PROGRESS_INTERVAL) {
+            self.reported = Some(now);
+            self.report(now);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.progress.finished = true;
+        self.report(Instant::now());
+    }
+
+    fn report(&mut self, now: Instant) {
+        self.progress.elapsed = now - self.started;
+        (self.callback)(&self.progress);
+    }
+}
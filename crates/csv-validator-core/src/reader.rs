@@ -1,7 +1,13 @@
+use bytes::{Bytes, BytesMut};
 use memchr::memchr;
 use memmap2::Mmap;
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Cursor, Read, Result};
+use std::io::{BufRead, BufReader, Cursor, ErrorKind, Read, Result};
+use std::ops::Range;
+use std::sync::Arc;
 
 pub struct OptimizedQuoteAwareReader<R: Read> {
     reader: BufReader<R>,
@@ -46,7 +52,7 @@ impl<R: Read> OptimizedQuoteAwareReader<R>{
             quote_count += bytecount::count(&self.buf, b'"');
 
             if quote_count % 2 == 0 {
-                if line_buf.ends_with(&[b'\n']) {
+                if line_buf.ends_with(b"\n") {
                     line_buf.pop();
                 }
                 return Ok(Some(line_buf));
@@ -55,6 +61,546 @@ impl<R: Read> OptimizedQuoteAwareReader<R>{
     }
 }
 
+/// Kernel computing the per-64-byte-block character masks, picked once at construction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kernel {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+/// Bit i set when byte i of a 64 byte block is a quote, a delimiter or a newline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockMasks {
+    quotes: u64,
+    delimiters: u64,
+    newlines: u64,
+}
+
+/// SIMD structural indexer, in the style of simdjson/simdcsv.
+///
+/// Finds the quotes, delimiters and newlines of 64 bytes at a time (SSE2/AVX2 on x86_64, NEON on aarch64,
+/// scalar elsewhere), turns the quote bits into an "inside quotes" mask with a prefix xor,
+/// and keeps the delimiters and newlines outside of quotes: the field and record boundaries.
+#[derive(Debug, Clone, Copy)]
+pub struct StructuralIndexer {
+    delimiter: u8,
+    quote: u8,
+    kernel: Kernel,
+}
+
+impl StructuralIndexer {
+    pub fn new(delimiter: u8, quote: u8) -> Self {
+        Self::with_kernel(delimiter, quote, Self::detect_kernel())
+    }
+
+    /// Portable fallback, ie. to compare against the SIMD kernels.
+    pub fn scalar(delimiter: u8, quote: u8) -> Self {
+        Self::with_kernel(delimiter, quote, Kernel::Scalar)
+    }
+
+    fn with_kernel(delimiter: u8, quote: u8, kernel: Kernel) -> Self {
+        Self { delimiter, quote, kernel }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn detect_kernel() -> Kernel {
+        if is_x86_feature_detected!("avx2") {
+            Kernel::Avx2
+        } else {
+            Kernel::Sse2
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect_kernel() -> Kernel {
+        Kernel::Neon
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn detect_kernel() -> Kernel {
+        Kernel::Scalar
+    }
+
+    /// Appends the positions of the delimiters and newlines outside of quotes in `data` to `out`.
+    /// `in_quotes` is the quote state at the start of `data`, and is left at the state at its end.
+    pub fn index(&self, data: &[u8], in_quotes: &mut bool, out: &mut Vec<usize>) {
+        self.for_each_block(data, in_quotes, |base, masks, outside| {
+            let mut structural = (masks.delimiters | masks.newlines) & outside;
+            while structural != 0 {
+                out.push(base + structural.trailing_zeros() as usize);
+                structural &= structural - 1;
+            }
+        });
+    }
+
+    /// Number of delimiters outside of quotes: a record has one field more.
+    pub fn count_delimiters(&self, data: &[u8]) -> usize {
+        let mut count = 0;
+        self.for_each_block(data, &mut false, |_, masks, outside| {
+            count += (masks.delimiters & outside).count_ones() as usize;
+        });
+        count
+    }
+
+    /// Field bounds of a record, split on the delimiters outside of quotes.
+    pub fn fields(&self, record: &[u8]) -> Vec<Range<usize>> {
+        let mut fields = Vec::new();
+        self.fields_into(record, &mut fields);
+        fields
+    }
+
+    /// Same as `fields`, reusing the `fields` buffer.
+    pub fn fields_into(&self, record: &[u8], fields: &mut Vec<Range<usize>>) {
+        fields.clear();
+        let mut start = 0;
+        self.for_each_block(record, &mut false, |base, masks, outside| {
+            let mut delimiters = masks.delimiters & outside;
+            while delimiters != 0 {
+                let delimiter = base + delimiters.trailing_zeros() as usize;
+                fields.push(start..delimiter);
+                start = delimiter + 1;
+                delimiters &= delimiters - 1;
+            }
+        });
+        fields.push(start..record.len());
+    }
+
+    /// Calls `f(block offset, masks, outside quotes mask)` for every 64 byte block of `data`,
+    /// the last one zero padded.
+    fn for_each_block<F: FnMut(usize, BlockMasks, u64)>(&self, data: &[u8], in_quotes: &mut bool, mut f: F) {
+        let mut carry = if *in_quotes { u64::MAX } else { 0 };
+        let mut padded = [0u8; 64];
+
+        for (i, chunk) in data.chunks(64).enumerate() {
+            let block: &[u8; 64] = match chunk.try_into() {
+                Ok(block) => block,
+                Err(_) => {
+                    // only the last chunk is short
+                    padded[..chunk.len()].copy_from_slice(chunk);
+                    &padded
+                }
+            };
+            let mut masks = self.masks(block);
+            if chunk.len() < 64 {
+                let valid = (1u64 << chunk.len()) - 1;
+                masks.quotes &= valid;
+                masks.delimiters &= valid;
+                masks.newlines &= valid;
+            }
+
+            // a quote toggles the state for every byte after it
+            let inside = prefix_xor(masks.quotes) ^ carry;
+            carry = ((inside as i64) >> 63) as u64;
+            f(i * 64, masks, !inside);
+        }
+
+        *in_quotes = carry != 0;
+    }
+
+    fn masks(&self, block: &[u8; 64]) -> BlockMasks {
+        match self.kernel {
+            Kernel::Scalar => masks_scalar(block, self.quote, self.delimiter),
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => unsafe { masks_sse2(block, self.quote, self.delimiter) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx2 => unsafe { masks_avx2(block, self.quote, self.delimiter) },
+            #[cfg(target_arch = "aarch64")]
+            Kernel::Neon => unsafe { masks_neon(block, self.quote, self.delimiter) },
+        }
+    }
+}
+
+/// Bit i of the result is the xor of bits 0..=i of `x`.
+fn prefix_xor(mut x: u64) -> u64 {
+    x ^= x << 1;
+    x ^= x << 2;
+    x ^= x << 4;
+    x ^= x << 8;
+    x ^= x << 16;
+    x ^= x << 32;
+    x
+}
+
+fn masks_scalar(block: &[u8; 64], quote: u8, delimiter: u8) -> BlockMasks {
+    let mut masks = BlockMasks { quotes: 0, delimiters: 0, newlines: 0 };
+    for (i, &b) in block.iter().enumerate() {
+        masks.quotes |= ((b == quote) as u64) << i;
+        masks.delimiters |= ((b == delimiter) as u64) << i;
+        masks.newlines |= ((b == b'\n') as u64) << i;
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn masks_sse2(block: &[u8; 64], quote: u8, delimiter: u8) -> BlockMasks {
+    use std::arch::x86_64::*;
+
+    let quotes = _mm_set1_epi8(quote as i8);
+    let delimiters = _mm_set1_epi8(delimiter as i8);
+    let newlines = _mm_set1_epi8(b'\n' as i8);
+    let mut masks = BlockMasks { quotes: 0, delimiters: 0, newlines: 0 };
+
+    for i in 0..4 {
+        // SAFETY: the 4 unaligned 16 byte loads stay inside the 64 byte block
+        let chunk = unsafe { _mm_loadu_si128(block.as_ptr().add(i * 16) as *const __m128i) };
+        let shift = i * 16;
+        masks.quotes |= (_mm_movemask_epi8(_mm_cmpeq_epi8(chunk, quotes)) as u16 as u64) << shift;
+        masks.delimiters |= (_mm_movemask_epi8(_mm_cmpeq_epi8(chunk, delimiters)) as u16 as u64) << shift;
+        masks.newlines |= (_mm_movemask_epi8(_mm_cmpeq_epi8(chunk, newlines)) as u16 as u64) << shift;
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn masks_avx2(block: &[u8; 64], quote: u8, delimiter: u8) -> BlockMasks {
+    use std::arch::x86_64::*;
+
+    let quotes = _mm256_set1_epi8(quote as i8);
+    let delimiters = _mm256_set1_epi8(delimiter as i8);
+    let newlines = _mm256_set1_epi8(b'\n' as i8);
+    let mut masks = BlockMasks { quotes: 0, delimiters: 0, newlines: 0 };
+
+    for i in 0..2 {
+        // SAFETY: the 2 unaligned 32 byte loads stay inside the 64 byte block
+        let chunk = unsafe { _mm256_loadu_si256(block.as_ptr().add(i * 32) as *const __m256i) };
+        let shift = i * 32;
+        masks.quotes |= (_mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, quotes)) as u32 as u64) << shift;
+        masks.delimiters |= (_mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, delimiters)) as u32 as u64) << shift;
+        masks.newlines |= (_mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, newlines)) as u32 as u64) << shift;
+    }
+    masks
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+unsafe fn masks_neon(block: &[u8; 64], quote: u8, delimiter: u8) -> BlockMasks {
+    use std::arch::aarch64::*;
+
+    // NEON has no movemask: weigh every matching byte by its bit, then add up each half
+    const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+    let weights = unsafe { vld1q_u8(WEIGHTS.as_ptr()) };
+    let movemask = |cmp: uint8x16_t| -> u64 {
+        let bits = vandq_u8(cmp, weights);
+        (vaddv_u8(vget_low_u8(bits)) as u64) | ((vaddv_u8(vget_high_u8(bits)) as u64) << 8)
+    };
+
+    let quotes = vdupq_n_u8(quote);
+    let delimiters = vdupq_n_u8(delimiter);
+    let newlines = vdupq_n_u8(b'\n');
+    let mut masks = BlockMasks { quotes: 0, delimiters: 0, newlines: 0 };
+
+    for i in 0..4 {
+        // SAFETY: the 4 16 byte loads stay inside the 64 byte block
+        let chunk = unsafe { vld1q_u8(block.as_ptr().add(i * 16)) };
+        let shift = i * 16;
+        masks.quotes |= movemask(vceqq_u8(chunk, quotes)) << shift;
+        masks.delimiters |= movemask(vceqq_u8(chunk, delimiters)) << shift;
+        masks.newlines |= movemask(vceqq_u8(chunk, newlines)) << shift;
+    }
+    masks
+}
+
+/// Record boundaries are the newlines outside of quotes.
+fn newline_indexer(quote: u8) -> StructuralIndexer {
+    StructuralIndexer::new(b'\n', quote)
+}
+
+/// A batch of consecutive records sharing one buffer: records are ranges into `data`,
+/// so building a batch doesn't allocate per record.
+pub struct RecordBatch {
+    /// line number of the first record
+    pub first_line_number: usize,
+    pub data: Bytes,
+    /// record bounds in `data`, without the trailing newline
+    pub records: Vec<Range<usize>>,
+}
+
+impl RecordBatch {
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn record(&self, index: usize) -> &[u8] {
+        &self.data[self.records[index].clone()]
+    }
+
+    /// (line number, record) pairs
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        self.records
+            .iter()
+            .enumerate()
+            .map(|(i, range)| (self.first_line_number + i, &self.data[range.clone()]))
+    }
+}
+
+/// Reads big chunks into a shared buffer and cuts them into `RecordBatch`es,
+/// with the same quote-aware record splitting as `OptimizedQuoteAwareReader`:
+/// a newline only ends a record outside of quotes.
+pub struct ChunkedRecordReader<R: Read> {
+    reader: R,
+    buf: BytesMut,
+    chunk_size: usize,
+    /// how much of `buf` is scanned, and whether its end is inside quotes: kept across reads and batches,
+    /// so every byte is scanned once
+    scanned: usize,
+    in_quotes: bool,
+    indexer: StructuralIndexer,
+    /// the newlines of the last scan, in `buf` as it was then: those from `next_newline` on aren't in a batch yet
+    newlines: Vec<usize>,
+    next_newline: usize,
+    /// the bytes split off the front of `buf` since the last scan
+    drained: usize,
+    eof: bool,
+    line_number: usize,
+}
+
+impl<R: Read> ChunkedRecordReader<R> {
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            buf: BytesMut::with_capacity(chunk_size),
+            chunk_size: chunk_size.max(1),
+            scanned: 0,
+            in_quotes: false,
+            indexer: newline_indexer(b'"'),
+            newlines: Vec::new(),
+            next_newline: 0,
+            drained: 0,
+            eof: false,
+            line_number: 0,
+        }
+    }
+
+    /// Newlines between this quote char don't end a record, `"` by default.
+    pub fn with_quote(self, quote: u8) -> Self {
+        Self { indexer: newline_indexer(quote), ..self }
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        let len = self.buf.len();
+        self.buf.resize(len + self.chunk_size, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buf[len..]) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let read = read.inspect_err(|_| self.buf.truncate(len))?;
+        self.buf.truncate(len + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> RecordBatchReader for ChunkedRecordReader<R> {
+    fn next_batch(&mut self, max_records: usize) -> Result<Option<RecordBatch>> {
+        let mut records = Vec::with_capacity(max_records.min(64 * 1024));
+        let mut record_start = 0;
+
+        loop {
+            let pending = &self.newlines[self.next_newline..];
+            for pos in pending.iter().take(max_records - records.len()).map(|p| p - self.drained) {
+                records.push(record_start..pos);
+                record_start = pos + 1;
+                self.next_newline += 1;
+            }
+
+            if records.len() == max_records {
+                break;
+            }
+            if self.scanned < self.buf.len() {
+                self.newlines.clear();
+                self.indexer.index(&self.buf[self.scanned..], &mut self.in_quotes, &mut self.newlines);
+                for pos in &mut self.newlines {
+                    *pos += self.scanned;
+                }
+                self.next_newline = 0;
+                self.drained = 0;
+                self.scanned = self.buf.len();
+                continue;
+            }
+
+            if self.eof {
+                // last record without a trailing newline
+                if record_start < self.buf.len() {
+                    records.push(record_start..self.buf.len());
+                    record_start = self.buf.len();
+                }
+                break;
+            }
+            self.fill()?;
+        }
+
+        if records.is_empty() {
+            return Ok(None);
+        }
+
+        let data = self.buf.split_to(record_start).freeze();
+        // the rest of the buffer is scanned already, up to `scanned`
+        self.scanned -= record_start;
+        self.drained += record_start;
+
+        let batch = RecordBatch {
+            first_line_number: self.line_number + 1,
+            data,
+            records,
+        };
+        self.line_number += batch.len();
+        Ok(Some(batch))
+    }
+}
+
+struct MmapChunk {
+    range: Range<usize>,
+    /// the chunk starts inside a quoted field
+    in_quotes: bool,
+}
+
+/// Splits a memory mapped file into record batches, finding the record boundaries on all threads of `pool`.
+///
+/// The file is cut into chunks after a newline. A first parallel pass counts the quotes of every chunk:
+/// the parity of all quotes before a chunk tells whether it starts inside a quoted field,
+/// so the chunks can then be scanned for record boundaries concurrently, a wave of chunks at a time.
+/// Records are handed out in file order, so line numbers stay globally correct.
+pub struct MmapRecordSplitter {
+    data: Bytes,
+    quote: u8,
+    pool: Arc<ThreadPool>,
+    chunks: Vec<MmapChunk>,
+    next_chunk: usize,
+    /// newlines outside of quotes, from the chunks scanned so far
+    boundaries: VecDeque<usize>,
+    record_start: usize,
+    line_number: usize,
+}
+
+impl MmapRecordSplitter {
+    pub fn open(path: &str, chunk_size: usize, pool: Arc<ThreadPool>) -> Result<Self> {
+        Self::open_with_quote(path, chunk_size, b'"', pool)
+    }
+
+    /// Same as `open`, for records quoted with `quote` instead of `"`.
+    pub fn open_with_quote(path: &str, chunk_size: usize, quote: u8, pool: Arc<ThreadPool>) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self::new_with_quote(Bytes::from_owner(mmap), chunk_size, quote, pool))
+    }
+
+    pub fn new(data: Bytes, chunk_size: usize, pool: Arc<ThreadPool>) -> Self {
+        Self::new_with_quote(data, chunk_size, b'"', pool)
+    }
+
+    pub fn new_with_quote(data: Bytes, chunk_size: usize, quote: u8, pool: Arc<ThreadPool>) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let mut ranges = Vec::with_capacity(data.len() / chunk_size + 1);
+        let mut start = 0;
+        while start < data.len() {
+            let cut = (start + chunk_size).min(data.len());
+            let end = match memchr(b'\n', &data[cut..]) {
+                Some(newline) => cut + newline + 1,
+                None => data.len(),
+            };
+            ranges.push(start..end);
+            start = end;
+        }
+
+        let odd_quotes: Vec<bool> = pool.install(|| {
+            ranges.par_iter().map(|range| bytecount::count(&data[range.clone()], quote) % 2 == 1).collect()
+        });
+
+        let mut in_quotes = false;
+        let chunks = ranges
+            .into_iter()
+            .zip(odd_quotes)
+            .map(|(range, odd)| {
+                let chunk = MmapChunk { range, in_quotes };
+                in_quotes ^= odd;
+                chunk
+            })
+            .collect();
+
+        Self {
+            data,
+            quote,
+            pool,
+            chunks,
+            next_chunk: 0,
+            boundaries: VecDeque::new(),
+            record_start: 0,
+            line_number: 0,
+        }
+    }
+
+    /// Scans the next wave of chunks, one per thread, for record boundaries.
+    fn scan_wave(&mut self) {
+        let wave_end = (self.next_chunk + self.pool.current_num_threads().max(1)).min(self.chunks.len());
+        let wave = &self.chunks[self.next_chunk..wave_end];
+        let data = &self.data;
+        let indexer = newline_indexer(self.quote);
+
+        let boundaries: Vec<Vec<usize>> = self.pool.install(|| {
+            wave.par_iter()
+                .map(|chunk| {
+                    let mut in_quotes = chunk.in_quotes;
+                    let mut boundaries = Vec::new();
+                    indexer.index(&data[chunk.range.clone()], &mut in_quotes, &mut boundaries);
+                    boundaries.iter_mut().for_each(|pos| *pos += chunk.range.start);
+                    boundaries
+                })
+                .collect()
+        });
+
+        self.boundaries.extend(boundaries.into_iter().flatten());
+        self.next_chunk = wave_end;
+    }
+}
+
+impl RecordBatchReader for MmapRecordSplitter {
+    fn next_batch(&mut self, max_records: usize) -> Result<Option<RecordBatch>> {
+        let batch_start = self.record_start;
+        let mut records = Vec::with_capacity(max_records.min(64 * 1024));
+
+        while records.len() < max_records {
+            if let Some(boundary) = self.boundaries.pop_front() {
+                records.push(self.record_start - batch_start..boundary - batch_start);
+                self.record_start = boundary + 1;
+            } else if self.next_chunk < self.chunks.len() {
+                self.scan_wave();
+            } else {
+                // last record without a trailing newline
+                if self.record_start < self.data.len() {
+                    records.push(self.record_start - batch_start..self.data.len() - batch_start);
+                    self.record_start = self.data.len();
+                }
+                break;
+            }
+        }
+
+        if records.is_empty() {
+            return Ok(None);
+        }
+
+        let batch = RecordBatch {
+            first_line_number: self.line_number + 1,
+            data: self.data.slice(batch_start..self.record_start.min(self.data.len())),
+            records,
+        };
+        self.line_number += batch.len();
+        Ok(Some(batch))
+    }
+}
+
 pub struct QuoteAwareBufferedReader {
     reader: BufReader<File>,
     buffer: Vec<u8>,
@@ -107,7 +653,7 @@ impl QuoteAwareBufferedReader {
                 b'\n' => {
                     line_buf.push(b);
                     if !self.in_quotes {
-                        if line_buf.ends_with(&[b'\n']) {
+                        if line_buf.ends_with(b"\n") {
                             line_buf.pop(); // explicitly remove newline for consistency
                         }
                         return Ok(Some(line_buf));
@@ -139,7 +685,7 @@ impl FastBufferedReader {
         if bytes_read == 0 {
             return Ok(None);
         }
-        if self.buffer.ends_with(&[b'\n']) {
+        if self.buffer.ends_with(b"\n") {
             self.buffer.pop();
         }
         Ok(Some(&self.buffer))
@@ -163,7 +709,7 @@ impl MmapBufferedReader {
 }
 
 impl BufferedLineReader for MmapBufferedReader {
-    fn next_line(&mut self, buf: &mut String) -> Result<bool> {
+    fn next_line(&mut self, buf: &mut String) -> crate::Result<bool> {
         buf.clear();
 
         if self.position >= self.mmap.len() {
@@ -189,12 +735,31 @@ impl BufferedLineReader for MmapBufferedReader {
     }
 }
 
+/// Source of record batches for the engine.
+pub trait RecordBatchReader {
+    /// Up to `max_records` records, None at end of input.
+    fn next_batch(&mut self, max_records: usize) -> Result<Option<RecordBatch>>;
+}
+
+/// Reads text lines; a line that isn't UTF-8 is an `Error::Encoding`.
 pub trait BufferedLineReader {
-    fn next_line(&mut self, buf: &mut String) -> Result<bool>;
+    fn next_line(&mut self, buf: &mut String) -> crate::Result<bool>;
+}
+
+/// `read_line`, with the number of the line that isn't UTF-8 in the error.
+fn read_text_line<R: BufRead>(reader: &mut R, buf: &mut String, line_number: &mut usize) -> crate::Result<bool> {
+    buf.clear(); // explicitly clear buffer before reuse
+    *line_number += 1;
+    match reader.read_line(buf) {
+        Ok(bytes) => Ok(bytes != 0), // EOF if 0 bytes read
+        Err(e) if e.kind() == ErrorKind::InvalidData => Err(crate::Error::Encoding { line: *line_number }),
+        Err(e) => Err(e.into()),
+    }
 }
 
 pub struct FileBufferedReader {
     reader: BufReader<File>,
+    line_number: usize,
 }
 
 impl FileBufferedReader {
@@ -202,35 +767,34 @@ impl FileBufferedReader {
         let file = File::open(path)?;
         Ok(Self {
             reader: BufReader::with_capacity(buffer_capacity, file),
+            line_number: 0,
         })
     }
 }
 
 /// Naive buffered reader for files
 impl BufferedLineReader for FileBufferedReader {
-    fn next_line(&mut self, buf: &mut String) -> Result<bool> {
-        buf.clear(); // explicitly clear buffer before reuse
-        let bytes = self.reader.read_line(buf)?;
-        Ok(bytes != 0) // EOF if 0 bytes read
+    fn next_line(&mut self, buf: &mut String) -> crate::Result<bool> {
+        read_text_line(&mut self.reader, buf, &mut self.line_number)
     }
 }
 
 pub struct MemoryBufferedReader<'a> {
     reader: BufReader<Cursor<&'a [u8]>>,
+    line_number: usize,
 }
 
 impl<'a> MemoryBufferedReader<'a> {
     pub fn new(buffer: &'a [u8], buffer_capacity: usize) -> Self {
         Self {
             reader: BufReader::with_capacity(buffer_capacity, Cursor::new(buffer)),
+            line_number: 0,
         }
     }
 }
 
 impl<'a> BufferedLineReader for MemoryBufferedReader<'a> {
-    fn next_line(&mut self, buf: &mut String) -> Result<bool> {
-        buf.clear();
-        let bytes = self.reader.read_line(buf)?;
-        Ok(bytes != 0)
+    fn next_line(&mut self, buf: &mut String) -> crate::Result<bool> {
+        read_text_line(&mut self.reader, buf, &mut self.line_number)
     }
 }
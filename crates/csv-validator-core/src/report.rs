@@ -0,0 +1,65 @@
+use crate::{Severity, ValidationIssue};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Summary of a validation run.
+/// The counts cover the records validated: all of them, unless the run was truncated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// records validated, the header not included
+    pub records: u64,
+    pub bytes: u64,
+    /// records with at least one issue, the header not included
+    pub records_with_issues: u64,
+    /// issues reported per validator name, those the issue limits left out aren't counted
+    pub issues_per_validator: BTreeMap<&'static str, u64>,
+    /// issues reported per severity, counted like `issues_per_validator`
+    pub issues_per_severity: BTreeMap<Severity, u64>,
+    /// issues reported per zero-based column, for the issues with a position
+    pub issues_per_column: BTreeMap<usize, u64>,
+    /// column names, from the header when `has_header` is set
+    pub columns: Vec<String>,
+    pub elapsed: Duration,
+    /// `max_issues`, `max_issues_per_validator` or `fail_fast` kicked in: issues were left out,
+    /// or the validation stopped early and the issues (and outputs) end where it stopped.
+    pub truncated: bool,
+}
+
+impl ValidationReport {
+    /// Bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { self.bytes as f64 / seconds } else { 0.0 }
+    }
+
+    /// Records per second.
+    pub fn records_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 { self.records as f64 / seconds } else { 0.0 }
+    }
+
+    /// Share of the records with issues, between 0 and 1.
+    pub fn issue_rate(&self) -> f64 {
+        if self.records > 0 { self.records_with_issues as f64 / self.records as f64 } else { 0.0 }
+    }
+
+    /// Header name of a column, when known.
+    pub fn column_name(&self, column: usize) -> Option<&str> {
+        self.columns.get(column).map(String::as_str)
+    }
+
+    pub fn total_issues(&self) -> u64 {
+        self.issues_per_validator.values().sum()
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.issues_per_severity.get(&Severity::Error).copied().unwrap_or_default()
+    }
+}
+
+/// What `validate_file` and `validate_reader` return: the issues, and the report of the run.
+#[derive(Debug)]
+pub struct ValidationOutcome {
+    pub issues: Vec<ValidationIssue>,
+    pub report: ValidationReport,
+}
@@ -0,0 +1,142 @@
+use crate::{Result, StructuralIndexer, ValidationIssue, Validator};
+use aho_corasick::AhoCorasick;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// A match of one of a validator's own literal patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiteralMatch {
+    /// index into the validator's `literal_patterns`
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A record as scanned once for all validators, seen from one validator:
+/// only its own pattern matches, and the fields split on its own delimiter.
+pub struct ScannedRecord<'a> {
+    pub line: &'a [u8],
+    /// non-overlapping, in line order, the same matches as searching for the validator's patterns alone
+    pub matches: &'a [LiteralMatch],
+    /// None when the validator has no `field_delimiter`, or is the only one splitting on it
+    pub fields: Option<&'a [Range<usize>]>,
+}
+
+/// Scans every record once for all validators: the literal patterns of all validators go into one
+/// Aho-Corasick automaton, and the fields are tokenized once per distinct delimiter,
+/// so adding validators doesn't add a full rescan of every line each.
+pub struct FusedScanner {
+    validators: Arc<Vec<Box<dyn Validator>>>,
+    /// None when no validator has literal patterns
+    automaton: Option<AhoCorasick>,
+    /// fused pattern id -> (validator index, pattern index within the validator)
+    owners: Vec<(usize, usize)>,
+    /// one per delimiter shared by several validators
+    indexers: Vec<StructuralIndexer>,
+    /// validator index -> index of the indexer splitting its fields
+    tokenizers: Vec<Option<usize>>,
+}
+
+/// Per thread scratch space of `FusedScanner`, reused from record to record.
+#[derive(Default)]
+pub struct ScanBuffers {
+    matches: Vec<Vec<LiteralMatch>>,
+    /// per validator: its next match can't start before this, to keep its matches non-overlapping
+    resume_at: Vec<usize>,
+    fields: Vec<Vec<Range<usize>>>,
+}
+
+impl FusedScanner {
+    pub fn new(validators: Arc<Vec<Box<dyn Validator>>>) -> Result<Self> {
+        let mut patterns = Vec::new();
+        let mut owners = Vec::new();
+        for (v, validator) in validators.iter().enumerate() {
+            for (p, pattern) in validator.literal_patterns().iter().enumerate() {
+                patterns.push(pattern.as_str());
+                owners.push((v, p));
+            }
+        }
+
+        // fields are only tokenized for a delimiter shared by several validators:
+        // one alone on its delimiter is better off with its own, cheaper, scan (ie. only counting)
+        let dialects: Vec<Option<(u8, u8)>> =
+            validators.iter().map(|v| v.field_delimiter().map(|d| (d, v.field_quote()))).collect();
+        let mut shared = Vec::new();
+        let tokenizers = dialects
+            .iter()
+            .map(|&dialect| {
+                let dialect = dialect.filter(|&d| dialects.iter().filter(|&&o| o == Some(d)).count() > 1)?;
+                Some(shared.iter().position(|&d| d == dialect).unwrap_or_else(|| {
+                    shared.push(dialect);
+                    shared.len() - 1
+                }))
+            })
+            .collect();
+        let indexers = shared.into_iter().map(|(delimiter, quote)| StructuralIndexer::new(delimiter, quote)).collect();
+
+        let automaton = if patterns.is_empty() {
+            None
+        } else {
+            Some(AhoCorasick::new(&patterns)?)
+        };
+
+        Ok(Self { validators, automaton, owners, indexers, tokenizers })
+    }
+
+    pub fn validators(&self) -> &[Box<dyn Validator>] {
+        &self.validators
+    }
+
+    /// Scans `line` once and runs every validator's `validate_record` on the result.
+    pub fn validate(&self, line: &[u8], line_number: usize, buffers: &mut ScanBuffers, issues: &mut Vec<ValidationIssue>) {
+        self.validate_each(line, line_number, buffers, issues, |_, _, _| {});
+    }
+
+    /// Same as `validate`, calling `done` after each validator with its index, the record it got and the issues so far.
+    pub(crate) fn validate_each(
+        &self,
+        line: &[u8],
+        line_number: usize,
+        buffers: &mut ScanBuffers,
+        issues: &mut Vec<ValidationIssue>,
+        mut done: impl FnMut(usize, &ScannedRecord, &[ValidationIssue]),
+    ) {
+        self.scan(line, buffers);
+        for (v, validator) in self.validators.iter().enumerate() {
+            let record = ScannedRecord {
+                line,
+                matches: &buffers.matches[v],
+                fields: self.tokenizers[v].map(|t| buffers.fields[t].as_slice()),
+            };
+            validator.validate_record(&record, line_number, issues);
+            done(v, &record, issues);
+        }
+    }
+
+    fn scan(&self, line: &[u8], buffers: &mut ScanBuffers) {
+        buffers.matches.resize_with(self.validators.len(), Vec::new);
+        buffers.resume_at.resize(self.validators.len(), 0);
+        buffers.fields.resize_with(self.indexers.len(), Vec::new);
+
+        if let Some(automaton) = &self.automaton {
+            for matches in &mut buffers.matches {
+                matches.clear();
+            }
+            buffers.resume_at.fill(0);
+
+            // All matches of all patterns, by end position. Keeping, per validator, the first match
+            // that starts after its previous one gives the same matches as its own non-overlapping search.
+            for mat in automaton.find_overlapping_iter(line) {
+                let (v, pattern) = self.owners[mat.pattern().as_usize()];
+                if mat.start() >= buffers.resume_at[v] {
+                    buffers.matches[v].push(LiteralMatch { pattern, start: mat.start(), end: mat.end() });
+                    buffers.resume_at[v] = mat.end().max(mat.start() + 1);
+                }
+            }
+        }
+
+        for (indexer, fields) in self.indexers.iter().zip(&mut buffers.fields) {
+            indexer.fields_into(line, fields);
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use crate::ValidationIssue;
+use std::io::Result;
+
+/// Receives the issues batch by batch, as the validation progresses,
+/// instead of collecting every issue of the file in memory first.
+pub trait IssueSink {
+    fn consume(&mut self, issues: Vec<ValidationIssue>) -> Result<()>;
+}
+
+/// Collect everything, which is what `validate_file` does.
+impl IssueSink for Vec<ValidationIssue> {
+    fn consume(&mut self, issues: Vec<ValidationIssue>) -> Result<()> {
+        self.extend(issues);
+        Ok(())
+    }
+}
+
+/// Any closure taking a batch of issues is a sink.
+impl<F> IssueSink for F
+where
+    F: FnMut(Vec<ValidationIssue>) -> Result<()>,
+{
+    fn consume(&mut self, issues: Vec<ValidationIssue>) -> Result<()> {
+        self(issues)
+    }
+}
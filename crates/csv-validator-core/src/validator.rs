@@ -1,10 +1,79 @@
-use crate::ValidationIssue;
-use crossbeam_channel::Sender;
+use crate::{ScannedRecord, ValidationIssue, ValidationResult};
+
+/// The values of one of a validator's unique keys in a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueValues {
+    /// which of the validator's keys, counted from 0
+    pub key: usize,
+    pub values: Vec<String>,
+    /// where the first field of the key starts in the line
+    pub position: Option<usize>,
+}
 
 /// Validator explicitly takes &[u8] input and appends issues to provided issue vector.
 pub trait Validator: Send + Sync {
     fn validate(&self, line: &[u8], line_number: usize, issues: &mut Vec<ValidationIssue>);
+
+    /// Fix mode: report issues like `validate` and hand the line on to the next validator in the chain.
+    /// Validators that know how to repair a line return the rewritten line, the default passes it on untouched.
+    fn fix<'a>(
+        &self,
+        result: ValidationResult<'a>,
+        line_number: usize,
+        issues: &mut Vec<ValidationIssue>,
+    ) -> ValidationResult<'a> {
+        self.validate(&result.line, line_number, issues);
+        result
+    }
+
+    /// Literal patterns this validator searches every line for. The engine fuses the patterns of all
+    /// validators into one automaton and hands each validator its own matches in `validate_record`.
+    fn literal_patterns(&self) -> &[String] {
+        &[]
+    }
+
+    /// Delimiter this validator splits fields on, so the engine tokenizes each record once for all validators.
+    fn field_delimiter(&self) -> Option<u8> {
+        None
+    }
+
+    /// Quote char of the fields split on `field_delimiter`: validators only share a tokenization when both match.
+    fn field_quote(&self) -> u8 {
+        b'"'
+    }
+
+    /// Same as `validate`, on a record the engine scanned once for all validators.
+    /// Validators declaring `literal_patterns` or a `field_delimiter` use the scan instead of rescanning the line.
+    fn validate_record(&self, record: &ScannedRecord, line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        self.validate(record.line, line_number, issues);
+    }
+
+    /// Looks up the columns the validator refers to by name in the header line, `None` without a header.
+    /// The engine calls it before the first record and validates with the returned validator instead,
+    /// when there is one.
+    fn resolve_columns(&self, header: Option<&[u8]>) -> crate::Result<Option<Box<dyn Validator>>> {
+        let _ = header;
+        Ok(None)
+    }
+
+    /// Validators with keys whose values no two records may share, ie. a primary key. The engine checks them across
+    /// all records, in line order, so a duplicate is always reported on the later line.
+    fn has_unique_keys(&self) -> bool {
+        false
+    }
+
+    /// The values of the unique keys in a record, as the validator validated it: in fix mode, the line as the validators
+    /// before it fixed it. Records with no value for a key are left out of it.
+    fn unique_values(&self, record: &ScannedRecord, line_number: usize, values: &mut Vec<UniqueValues>) {
+        let _ = (record, line_number, values);
+    }
+
+    /// The issue of a record repeating the unique key `values` of the record on `first_line`.
+    fn duplicate_issue(&self, values: &UniqueValues, line_number: usize, first_line: usize) -> Option<ValidationIssue> {
+        let _ = (values, line_number, first_line);
+        None
+    }
+
     fn name(&self) -> &'static str;
     fn clone_box(&self) -> Box<dyn Validator>;
 }
-
@@ -1,55 +1,325 @@
-use crate::{FieldCountValidator, Validator, IllegalCharactersValidator, LineLengthValidator};
+use crate::{Column, ColumnsValidator, CommonSettings, Dialect, FieldCountRepair, ForeignKey, Result, FieldCountValidator, Validator, IllegalCharactersValidator, LineLengthValidator, Severity, read_reference};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// A validator and its settings, as written in a config file:
+///
+/// ```yaml
+/// - type: field_count
+///   expected: 50
+///   repair: pad
+///   severity: warning
+/// ```
+///
+/// `id`, `enabled` and `severity` are optional, so are `replace_with` and `repair`, which are only used in fix mode.
+/// A validator's `common` settings (separator, quote_char, has_header) override the top-level ones, see `Dialect::resolve`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
 pub enum ValidatorSpec {
+    /// Reports every occurrence of the illegal characters or strings.
     IllegalChars {
+        /// names the validator, for an including config to replace it or a profile to pick it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        /// the characters or strings to report, matched literally
+        #[cfg_attr(feature = "schema", schemars(length(min = 1), inner(length(min = 1))))]
         illegal_chars: Vec<String>,
+        /// replacements used in fix mode, one per illegal char, empty to only report
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        replace_with: Vec<String>,
+        /// Deprecated and ignored, still read so older configs load: fix mode is on when there is a fixed output
+        /// (`--fix-output`), and replaces what `replace_with` says.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[cfg_attr(feature = "schema", schemars(extend("deprecated" = true)))]
+        fix: Option<bool>,
+        /// false to keep the validator in the config without running it
+        #[serde(default = "enabled")]
         enabled: bool,
+        /// severity of the issues found: error, warning or info
+        #[serde(default)]
+        severity: Severity,
+        /// settings of this validator, over the top-level ones
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        common: Option<CommonSettings>,
     },
+    /// Reports records with another number of fields.
     FieldCount {
+        /// names the validator, for an including config to replace it or a profile to pick it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        /// the number of fields of every record
+        #[cfg_attr(feature = "schema", schemars(range(min = 1)))]
         expected: usize,
+        /// how to repair a mismatch in fix mode, None to only report
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        repair: Option<FieldCountRepair>,
+        /// false to keep the validator in the config without running it
+        #[serde(default = "enabled")]
         enabled: bool,
+        /// severity of the issues found: error, warning or info
+        #[serde(default)]
+        severity: Severity,
+        /// settings of this validator, over the top-level ones
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        common: Option<CommonSettings>,
     },
+    /// Reports lines longer than the maximum, in bytes.
     LineLength {
+        /// names the validator, for an including config to replace it or a profile to pick it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        /// false to keep the validator in the config without running it
+        #[serde(default = "enabled")]
         enabled: bool,
+        /// the maximum length of a line, in bytes
+        #[cfg_attr(feature = "schema", schemars(range(min = 1)))]
         max_length: usize,
-    }
+        /// severity of the issues found: error, warning or info
+        #[serde(default)]
+        severity: Severity,
+        /// settings of this validator, over the top-level ones
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        common: Option<CommonSettings>,
+    },
+    /// Checks the values of each column, in order: their type and constraints. With a header, checks the column names.
+    Columns {
+        /// names the validator, for an including config to replace it or a profile to pick it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[cfg_attr(feature = "schema", schemars(length(min = 1)))]
+        columns: Vec<Column>,
+        /// the values that stand for no value
+        #[serde(default = "missing_values")]
+        missing_values: Vec<String>,
+        /// columns whose values together are unique, ie. a primary key
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        unique_keys: Vec<Vec<String>>,
+        /// columns whose values must appear in another CSV file
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        foreign_keys: Vec<ForeignKey>,
+        /// false to keep the validator in the config without running it
+        #[serde(default = "enabled")]
+        enabled: bool,
+        /// severity of the issues found: error, warning or info
+        #[serde(default)]
+        severity: Severity,
+        /// settings of this validator, over the top-level ones
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        common: Option<CommonSettings>,
+    },
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn missing_values() -> Vec<String> {
+    vec![String::new()]
 }
 
 impl ValidatorSpec {
     pub fn new_illegal_chars(chars: Vec<String>) -> Self {
         Self::IllegalChars {
+            id: None,
             illegal_chars: chars,
+            replace_with: Vec::new(),
+            fix: None,
             enabled: true,
+            severity: Severity::Error,
+            common: None,
+        }
+    }
+
+    pub fn new_illegal_chars_with_replacements(chars: Vec<String>, replace_with: Vec<String>) -> Self {
+        Self::IllegalChars {
+            id: None,
+            illegal_chars: chars,
+            replace_with,
+            fix: None,
+            enabled: true,
+            severity: Severity::Error,
+            common: None,
         }
     }
 
     pub fn new_field_count(expected: usize) -> Self {
         Self::FieldCount {
+            id: None,
             expected,
+            repair: None,
             enabled: true,
+            severity: Severity::Error,
+            common: None,
+        }
+    }
+
+    pub fn new_field_count_with_repair(expected: usize, repair: FieldCountRepair) -> Self {
+        Self::FieldCount {
+            id: None,
+            expected,
+            repair: Some(repair),
+            enabled: true,
+            severity: Severity::Error,
+            common: None,
         }
     }
 
     pub fn new_line_length(max_length: usize) -> Self {
         Self::LineLength {
+            id: None,
             max_length,
             enabled: true,
+            severity: Severity::Error,
+            common: None,
+        }
+    }
+
+    pub fn new_columns(columns: Vec<Column>) -> Self {
+        Self::Columns {
+            id: None,
+            columns,
+            missing_values: missing_values(),
+            unique_keys: Vec::new(),
+            foreign_keys: Vec::new(),
+            enabled: true,
+            severity: Severity::Error,
+            common: None,
         }
     }
 
-    pub fn into_validator(self, separator: u8) -> Box<dyn Validator> {
+    /// The `type` of the spec in a config file, ie. `field_count`.
+    pub fn type_name(&self) -> &'static str {
         match self {
-            ValidatorSpec::IllegalChars { illegal_chars, .. } => {
+            ValidatorSpec::IllegalChars { .. } => "illegal_chars",
+            ValidatorSpec::FieldCount { .. } => "field_count",
+            ValidatorSpec::LineLength { .. } => "line_length",
+            ValidatorSpec::Columns { .. } => "columns",
+        }
+    }
+
+    /// The `id` of the spec in a config file, if it has one.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            ValidatorSpec::IllegalChars { id, .. }
+            | ValidatorSpec::FieldCount { id, .. }
+            | ValidatorSpec::LineLength { id, .. }
+            | ValidatorSpec::Columns { id, .. } => id.as_deref(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            ValidatorSpec::IllegalChars { enabled, .. }
+            | ValidatorSpec::FieldCount { enabled, .. }
+            | ValidatorSpec::LineLength { enabled, .. }
+            | ValidatorSpec::Columns { enabled, .. } => *enabled,
+        }
+    }
+
+    pub fn common(&self) -> Option<&CommonSettings> {
+        match self {
+            ValidatorSpec::IllegalChars { common, .. }
+            | ValidatorSpec::FieldCount { common, .. }
+            | ValidatorSpec::LineLength { common, .. }
+            | ValidatorSpec::Columns { common, .. } => common.as_ref(),
+        }
+    }
+
+    pub fn with_common(mut self, common: CommonSettings) -> Self {
+        match &mut self {
+            ValidatorSpec::IllegalChars { common: c, .. }
+            | ValidatorSpec::FieldCount { common: c, .. }
+            | ValidatorSpec::LineLength { common: c, .. }
+            | ValidatorSpec::Columns { common: c, .. } => *c = Some(common),
+        }
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        match &mut self {
+            ValidatorSpec::IllegalChars { enabled: e, .. }
+            | ValidatorSpec::FieldCount { enabled: e, .. }
+            | ValidatorSpec::LineLength { enabled: e, .. }
+            | ValidatorSpec::Columns { enabled: e, .. } => *e = enabled,
+        }
+        self
+    }
+
+    /// Report the issues of this validator with `severity` instead of as errors.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        match &mut self {
+            ValidatorSpec::IllegalChars { severity: s, .. }
+            | ValidatorSpec::FieldCount { severity: s, .. }
+            | ValidatorSpec::LineLength { severity: s, .. }
+            | ValidatorSpec::Columns { severity: s, .. } => *s = severity,
+        }
+        self
+    }
+
+    /// Fails on settings the validator can't work with, ie. an empty pattern.
+    /// Builds the validator even when disabled, `into_validators` leaves those out.
+    pub fn into_validator(self, separator: u8) -> Result<Box<dyn Validator>> {
+        self.into_validator_with_quote(separator, b'"')
+    }
+
+    /// Same as `into_validator`, for fields quoted with `quote` instead of `"`.
+    pub fn into_validator_with_quote(self, separator: u8, quote: u8) -> Result<Box<dyn Validator>> {
+        let has_header = self.common().and_then(|common| common.has_header).unwrap_or_default();
+        self.into_validator_with_dialect(&Dialect { separator, quote_char: quote, has_header, skip_rows: 0 })
+    }
+
+    /// Same as `into_validator`, for files in `dialect`: the columns validator checks the header, and reads
+    /// the files its foreign keys reference in that dialect.
+    pub fn into_validator_with_dialect(self, dialect: &Dialect) -> Result<Box<dyn Validator>> {
+        let Dialect { separator, quote_char: quote, has_header, skip_rows } = *dialect;
+        Ok(match self {
+            ValidatorSpec::IllegalChars { illegal_chars, replace_with, severity, .. } => {
                 let refs = illegal_chars.iter().map(AsRef::as_ref).collect::<Vec<_>>();
-                Box::new(IllegalCharactersValidator::new( &refs))
+                if replace_with.is_empty() {
+                    Box::new(IllegalCharactersValidator::new( &refs)?.with_severity(severity))
+                } else {
+                    let replacements = replace_with.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+                    Box::new(IllegalCharactersValidator::with_replacements(&refs, &replacements)?.with_severity(severity))
+                }
             }
-            ValidatorSpec::FieldCount { expected, .. } => {
-                Box::new(FieldCountValidator::new( expected, separator))
+            ValidatorSpec::FieldCount { expected, repair: None, severity, .. } => {
+                Box::new(FieldCountValidator::new( expected, separator)?.with_quote(quote).with_severity(severity))
             }
-            ValidatorSpec::LineLength { max_length, .. } => {
-                Box::new(LineLengthValidator::new( max_length))
+            ValidatorSpec::FieldCount { expected, repair: Some(repair), severity, .. } => {
+                Box::new(FieldCountValidator::with_repair( expected, separator, repair)?.with_quote(quote).with_severity(severity))
             }
-        }
+            ValidatorSpec::LineLength { max_length, severity, .. } => {
+                Box::new(LineLengthValidator::new( max_length)?.with_severity(severity))
+            }
+            ValidatorSpec::Columns { columns, missing_values, unique_keys, foreign_keys, severity, .. } => {
+                let mut validator = ColumnsValidator::new(columns, separator)?
+                    .with_quote(quote)
+                    .with_header(has_header)
+                    .with_skip_rows(skip_rows)
+                    .with_missing_values(missing_values)
+                    .with_severity(severity);
+                for key in &unique_keys {
+                    validator = validator.with_unique_key(key)?;
+                }
+                for key in foreign_keys {
+                    let values = read_reference(&key.reference, &key.reference_columns, dialect)?;
+                    let reference = format!("{} ({})", key.reference, key.reference_columns.join(", "));
+                    validator = validator.with_foreign_key(&key.columns, values, reference)?;
+                }
+                Box::new(validator)
+            }
+        })
     }
 }
+
+/// The validators of the enabled specs, in order, for files in `dialect`.
+pub fn into_validators(
+    specs: impl IntoIterator<Item = ValidatorSpec>,
+    dialect: &Dialect,
+) -> Result<Vec<Box<dyn Validator>>> {
+    specs
+        .into_iter()
+        .filter(ValidatorSpec::is_enabled)
+        .map(|spec| spec.into_validator_with_dialect(dialect))
+        .collect()
+}
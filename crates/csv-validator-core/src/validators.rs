@@ -1,21 +1,65 @@
-pub use crate::{ValidationIssue, Validator};
+pub use crate::{IssueCode, IssueStatus, ScannedRecord, Severity, ValidationIssue, ValidationResult, Validator};
+use crate::Error;
+use crate::reader::StructuralIndexer;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 use aho_corasick::AhoCorasick;
+use std::borrow::Cow;
+use std::ops::Range;
 
 #[derive(Clone)]
 pub struct IllegalCharactersValidator {
+    patterns: Vec<String>,
     matcher: AhoCorasick,
+    replace_with: Vec<String>,
+    severity: Severity,
 }
 
 impl IllegalCharactersValidator {
-    pub fn new<'a>(patterns: &[&'a str]) -> Self {
-        let matcher = AhoCorasick::new(patterns).expect("failed to build Aho-Corasick matcher");
-        Self { matcher }
+    /// Fails on an empty pattern, which would match everywhere.
+    pub fn new(patterns: &[&str]) -> crate::Result<Self> {
+        if patterns.iter().any(|p| p.is_empty()) {
+            return Err(Error::config("illegal_chars can't contain an empty pattern"));
+        }
+        let matcher = AhoCorasick::new(patterns)?;
+        Ok(Self {
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            matcher,
+            replace_with: Vec::new(),
+            severity: Severity::Error,
+        })
     }
 
-    fn clone_box(&self) -> Box<dyn Validator> {
-        println!("Cloning validator: {}", self.name());
+    /// In fix mode, every match of `patterns[i]` is replaced with `replace_with[i]`.
+    pub fn with_replacements(patterns: &[&str], replace_with: &[&str]) -> crate::Result<Self> {
+        if patterns.len() != replace_with.len() {
+            return Err(Error::config(format!(
+                "illegal_chars and replace_with must have the same length, got {} and {}",
+                patterns.len(),
+                replace_with.len()
+            )));
+        }
+        let mut validator = Self::new(patterns)?;
+        validator.replace_with = replace_with.iter().map(|r| r.to_string()).collect();
+        Ok(validator)
+    }
 
-        Box::new((*self).clone())
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    fn issue(&self, line: &[u8], found: Range<usize>, line_number: usize) -> ValidationIssue {
+        let illegal_char = std::str::from_utf8(&line[found.clone()]).unwrap_or("<invalid utf8>");
+        ValidationIssue::new(
+            self.name(),
+            IssueCode::ILLEGAL_CHARACTER,
+            line_number,
+            format!("Illegal character(s) '{}'", illegal_char),
+        )
+        .at(found.start)
+        .with_severity(self.severity)
+        .with_param("found", illegal_char)
     }
 }
 
@@ -23,51 +67,387 @@ impl Validator for IllegalCharactersValidator {
 
     fn validate(&self, line: &[u8], line_number: usize, issues: &mut Vec<ValidationIssue>) {
         for mat in self.matcher.find_iter(line) {
-            let illegal_str = &line[mat.start()..mat.end()];
-            let illegal_char = std::str::from_utf8(illegal_str).unwrap_or("<invalid utf8>");
+            issues.push(self.issue(line, mat.start()..mat.end(), line_number));
+        }
+    }
 
-            issues.push(ValidationIssue {
-                validator: self.name(),
-                line_number,
-                position: Some(mat.start()),
-                message: format!("Illegal character(s) '{}'", illegal_char),
-            });
+    fn validate_record(&self, record: &ScannedRecord, line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        for mat in record.matches {
+            issues.push(self.issue(record.line, mat.start..mat.end, line_number));
         }
     }
 
+    fn literal_patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    fn fix<'a>(
+        &self,
+        result: ValidationResult<'a>,
+        line_number: usize,
+        issues: &mut Vec<ValidationIssue>,
+    ) -> ValidationResult<'a> {
+        let issues_before = issues.len();
+        self.validate(&result.line, line_number, issues);
+
+        // nothing found or nothing to replace with: pass the line on as is
+        if issues.len() == issues_before || self.replace_with.is_empty() {
+            return result;
+        }
+
+        for issue in &mut issues[issues_before..] {
+            issue.status = IssueStatus::Fixed;
+        }
+
+        let fixed = self.matcher.replace_all_bytes(&result.line, &self.replace_with);
+        ValidationResult { line: Cow::Owned(fixed), ..result }
+    }
+
     fn name(&self) -> &'static str {
         "illegal_characters"
     }
 
     fn clone_box(&self) -> Box<dyn Validator> {
-        println!("Cloning validator: {}", self.name());
-
         Box::new(self.clone())
     }
 }
 
+/// How `FieldCountValidator` repairs a line in fix mode.
+/// Fields are split on the delimiter, the same way they are counted.
+/// Written as in `FromStr`, ie. `repair: merge:2` in a config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum FieldCountRepair {
+    /// append empty fields to lines with too few fields
+    Pad,
+    /// drop the extra fields of lines with too many fields
+    Truncate,
+    /// merge the extra fields into this column, as one quoted field
+    Merge(MergeColumn),
+    /// send lines with a wrong field count to the reject file
+    Reject,
+}
+
+/// The column `FieldCountRepair::Merge` merges into.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeColumn {
+    /// zero-based
+    Index(usize),
+    /// looked up in the header by the engine, before the first record
+    Name(String),
+}
+
+impl fmt::Display for MergeColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeColumn::Index(index) => write!(f, "{}", index),
+            MergeColumn::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl FieldCountRepair {
+    /// Merge into a named column, looked up in the header line.
+    pub fn merge_into(header: &[u8], delimiter: u8, quote: u8, column: &str) -> Option<Self> {
+        let header = header.strip_suffix(b"\r").unwrap_or(header);
+        StructuralIndexer::new(delimiter, quote)
+            .fields(header)
+            .into_iter()
+            .position(|name| trim_quotes(&header[name], quote) == column.as_bytes())
+            .map(|index| FieldCountRepair::Merge(MergeColumn::Index(index)))
+    }
+}
+
+impl FromStr for FieldCountRepair {
+    type Err = String;
+
+    /// `pad`, `truncate`, `reject` or `merge:<column name or index>`: a number is an index
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pad" => Ok(FieldCountRepair::Pad),
+            "truncate" => Ok(FieldCountRepair::Truncate),
+            "reject" => Ok(FieldCountRepair::Reject),
+            _ => match s.strip_prefix("merge:").filter(|column| !column.is_empty()) {
+                Some(column) => Ok(FieldCountRepair::Merge(match column.parse() {
+                    Ok(index) => MergeColumn::Index(index),
+                    Err(_) => MergeColumn::Name(column.to_string()),
+                })),
+                None => Err(format!(
+                    "invalid field count repair '{}', expected pad, truncate, reject or merge:<column name or index>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for FieldCountRepair {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for FieldCountRepair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldCountRepair::Pad => f.write_str("pad"),
+            FieldCountRepair::Truncate => f.write_str("truncate"),
+            FieldCountRepair::Reject => f.write_str("reject"),
+            FieldCountRepair::Merge(column) => write!(f, "merge:{}", column),
+        }
+    }
+}
+
+impl From<FieldCountRepair> for String {
+    fn from(repair: FieldCountRepair) -> Self {
+        repair.to_string()
+    }
+}
+
+/// A string in the form `FromStr` parses.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for FieldCountRepair {
+    fn schema_name() -> Cow<'static, str> {
+        "FieldCountRepair".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "How to repair a field count mismatch in fix mode: pad, truncate, reject or merge:<column name or index>",
+            "type": "string",
+            "pattern": "^(pad|truncate|reject|merge:.+)$"
+        })
+    }
+}
+
+/// The line without its `\r`, and the line end to write after it: `\r\n` for a line that had one.
+/// The reader keeps the `\r` of CRLF line ends on the records.
+pub(crate) fn split_terminator(line: &[u8]) -> (&[u8], &'static [u8]) {
+    match line.strip_suffix(b"\r") {
+        Some(line) => (line, b"\r\n"),
+        None => (line, b"\n"),
+    }
+}
+
+pub(crate) fn trim_quotes(field: &[u8], quote: u8) -> &[u8] {
+    match field {
+        [first, inner @ .., last] if *first == quote && *last == quote => inner,
+        _ => field,
+    }
+}
+
+/// Counts the fields of a record, delimiters inside quoted fields don't count.
 #[derive(Clone)]
 pub struct FieldCountValidator {
     expected_fields: usize,
     delimiter: u8,
+    quote: u8,
+    indexer: StructuralIndexer,
+    repair: Option<FieldCountRepair>,
+    severity: Severity,
 }
 
 impl FieldCountValidator {
-    pub fn new(expected_fields: usize, delimiter: u8) -> Self {
-        Self {expected_fields, delimiter }
+    /// Fails on 0 expected fields: every record has at least one.
+    pub fn new(expected_fields: usize, delimiter: u8) -> crate::Result<Self> {
+        if expected_fields == 0 {
+            return Err(Error::config("the expected field count must be at least 1"));
+        }
+        Ok(Self {
+            expected_fields,
+            delimiter,
+            quote: b'"',
+            indexer: StructuralIndexer::new(delimiter, b'"'),
+            repair: None,
+            severity: Severity::Error,
+        })
+    }
+
+    /// Also fails on a merge into a column beyond the expected fields.
+    /// A merge into a named column is looked up in the header, see `resolve_columns`.
+    pub fn with_repair(expected_fields: usize, delimiter: u8, repair: FieldCountRepair) -> crate::Result<Self> {
+        if let FieldCountRepair::Merge(MergeColumn::Index(column)) = repair
+            && column >= expected_fields
+        {
+            return Err(Error::config(format!(
+                "can't merge into column {}, records have {} fields",
+                column, expected_fields
+            )));
+        }
+        Ok(Self { repair: Some(repair), ..Self::new(expected_fields, delimiter)? })
+    }
+
+    /// Delimiters between this quote char don't count, `"` by default.
+    pub fn with_quote(self, quote: u8) -> Self {
+        Self { quote, indexer: StructuralIndexer::new(self.delimiter, quote), ..self }
+    }
+
+    fn count_fields(&self, line: &[u8]) -> usize {
+        self.indexer.count_delimiters(line) + 1
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
+    fn check(&self, actual_fields: usize, line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        if actual_fields != self.expected_fields {
+            issues.push(self.mismatch(actual_fields, line_number));
+        }
+    }
+
+    fn mismatch(&self, actual_fields: usize, line_number: usize) -> ValidationIssue {
+        ValidationIssue::new(
+            self.name(),
+            IssueCode::FIELD_COUNT_MISMATCH,
+            line_number,
+            format!("Expected {} fields, found {}", self.expected_fields, actual_fields),
+        )
+        .with_severity(self.severity)
+        .with_param("expected", self.expected_fields)
+        .with_param("found", actual_fields)
+    }
+
+    /// Returns the repaired line, or None when the configured repair doesn't apply to this mismatch.
+    fn repair(&self, line: &[u8], actual_fields: usize) -> Option<Vec<u8>> {
+        let expected = self.expected_fields;
+        // the `\r` of a CRLF line end goes back after the repaired line
+        let (line, cr) = match line.strip_suffix(b"\r") {
+            Some(line) => (line, &b"\r"[..]),
+            None => (line, &b""[..]),
+        };
+        let mut fixed = match self.repair.as_ref()? {
+            FieldCountRepair::Pad if actual_fields < expected => {
+                let mut fixed = line.to_vec();
+                fixed.resize(line.len() + expected - actual_fields, self.delimiter);
+                fixed
+            }
+            FieldCountRepair::Truncate if actual_fields > expected => {
+                let fields = self.indexer.fields(line);
+                line[..fields[expected - 1].end].to_vec()
+            }
+            // a named column the engine didn't look up isn't merged into
+            FieldCountRepair::Merge(MergeColumn::Index(column)) if actual_fields > expected && *column < expected => {
+                let fields: Vec<&[u8]> = self.indexer.fields(line).into_iter().map(|field| &line[field]).collect();
+                let overflow = actual_fields - expected;
+                let merged_end = column + overflow + 1;
+
+                let mut fixed = Vec::with_capacity(line.len() + 2);
+                for (i, field) in fields[..*column].iter().enumerate() {
+                    if i > 0 {
+                        fixed.push(self.delimiter);
+                    }
+                    fixed.extend_from_slice(field);
+                }
+                if *column > 0 {
+                    fixed.push(self.delimiter);
+                }
+
+                // the merged field contains the delimiter, so it needs quoting
+                fixed.push(self.quote);
+                for (i, field) in fields[*column..merged_end].iter().enumerate() {
+                    if i > 0 {
+                        fixed.push(self.delimiter);
+                    }
+                    let unquoted = trim_quotes(field, self.quote);
+                    if unquoted.len() < field.len() {
+                        // quotes inside a quoted field are escaped already
+                        fixed.extend_from_slice(unquoted);
+                    } else {
+                        for &b in *field {
+                            if b == self.quote {
+                                fixed.push(self.quote);
+                            }
+                            fixed.push(b);
+                        }
+                    }
+                }
+                fixed.push(self.quote);
+
+                for field in &fields[merged_end..] {
+                    fixed.push(self.delimiter);
+                    fixed.extend_from_slice(field);
+                }
+                fixed
+            }
+            _ => return None,
+        };
+        fixed.extend_from_slice(cr);
+        Some(fixed)
     }
 }
 
 impl Validator for FieldCountValidator {
     fn validate(&self, line: &[u8], line_number: usize, issues: &mut Vec<ValidationIssue>) {
-        let actual_fields = bytecount::count(line, self.delimiter) + 1;
-        if actual_fields != self.expected_fields {
-            issues.push(ValidationIssue {
-                validator: self.name(),
-                line_number,
-                position: None,
-                message: format!("Expected {} fields, found {}", self.expected_fields, actual_fields),
-            });
+        self.check(self.count_fields(line), line_number, issues);
+    }
+
+    fn validate_record(&self, record: &ScannedRecord, line_number: usize, issues: &mut Vec<ValidationIssue>) {
+        let actual_fields = match record.fields {
+            Some(fields) => fields.len(),
+            None => self.count_fields(record.line),
+        };
+        self.check(actual_fields, line_number, issues);
+    }
+
+    fn field_delimiter(&self) -> Option<u8> {
+        Some(self.delimiter)
+    }
+
+    fn field_quote(&self) -> u8 {
+        self.quote
+    }
+
+    fn resolve_columns(&self, header: Option<&[u8]>) -> crate::Result<Option<Box<dyn Validator>>> {
+        let Some(FieldCountRepair::Merge(MergeColumn::Name(column))) = &self.repair else {
+            return Ok(None);
+        };
+        let Some(header) = header else {
+            return Err(Error::config(format!("can't merge into column '{}' without a header, use its index", column)));
+        };
+        let repair = FieldCountRepair::merge_into(header, self.delimiter, self.quote, column)
+            .ok_or_else(|| Error::config(format!("can't merge into column '{}', the header has no such column", column)))?;
+        let validator = Self::with_repair(self.expected_fields, self.delimiter, repair)?;
+        Ok(Some(Box::new(validator.with_quote(self.quote).with_severity(self.severity))))
+    }
+
+    fn fix<'a>(
+        &self,
+        result: ValidationResult<'a>,
+        line_number: usize,
+        issues: &mut Vec<ValidationIssue>,
+    ) -> ValidationResult<'a> {
+        let actual_fields = self.count_fields(&result.line);
+        if actual_fields == self.expected_fields {
+            return result;
+        }
+
+        let mut issue = self.mismatch(actual_fields, line_number);
+
+        if self.repair == Some(FieldCountRepair::Reject) {
+            issue.status = IssueStatus::Rejected;
+            issue.message.push_str(", rejected");
+            issues.push(issue);
+            return ValidationResult { rejected: true, ..result };
+        }
+
+        match self.repair(&result.line, actual_fields) {
+            Some(fixed) => {
+                issue.status = IssueStatus::Fixed;
+                issue.message.push_str(match self.repair {
+                    Some(FieldCountRepair::Pad) => ", padded with empty fields",
+                    Some(FieldCountRepair::Truncate) => ", truncated extra fields",
+                    _ => ", merged extra fields",
+                });
+                issues.push(issue);
+                ValidationResult { line: Cow::Owned(fixed), ..result }
+            }
+            None => {
+                issues.push(issue);
+                result
+            }
         }
     }
 
@@ -76,8 +456,6 @@ impl Validator for FieldCountValidator {
     }
 
     fn clone_box(&self) -> Box<dyn Validator> {
-        println!("Cloning validator: {}", self.name());
-
         Box::new(self.clone())
     }
 }
@@ -85,23 +463,37 @@ impl Validator for FieldCountValidator {
 #[derive(Clone)]
 pub struct LineLengthValidator {
     max_length: usize,
+    severity: Severity,
 }
 
 impl LineLengthValidator {
-    pub fn new( max_length: usize) -> Self {
-        Self {  max_length }
+    /// Fails on a maximum length of 0, which only allows empty lines.
+    pub fn new( max_length: usize) -> crate::Result<Self> {
+        if max_length == 0 {
+            return Err(Error::config("the maximum line length must be at least 1"));
+        }
+        Ok(Self {  max_length, severity: Severity::Error })
+    }
+
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
     }
 }
 
 impl Validator for LineLengthValidator {
     fn validate(&self, line: &[u8], line_number: usize, issues: &mut Vec<ValidationIssue>) {
         if line.len() > self.max_length {
-            issues.push(ValidationIssue {
-                validator: self.name(),
-                line_number,
-                position: None,
-                message: format!("Line length {} exceeds maximum {}", line.len(), self.max_length),
-            });
+            issues.push(
+                ValidationIssue::new(
+                    self.name(),
+                    IssueCode::LINE_TOO_LONG,
+                    line_number,
+                    format!("Line length {} exceeds maximum {}", line.len(), self.max_length),
+                )
+                .with_severity(self.severity)
+                .with_param("max_length", self.max_length)
+                .with_param("length", line.len()),
+            );
         }
     }
 
@@ -110,8 +502,6 @@ impl Validator for LineLengthValidator {
     }
 
     fn clone_box(&self) -> Box<dyn Validator> {
-        println!("Cloning validator: {}", self.name());
-
         Box::new(self.clone())
     }
 }
@@ -0,0 +1,63 @@
+use csv_validator_core::{CancellationToken, Error, ValidationIssue, ValidationOptions, Validator, validate_reader};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Takes a millisecond per line: validating the whole input would take minutes.
+#[derive(Clone)]
+struct SlowValidator;
+
+impl Validator for SlowValidator {
+    fn validate(&self, _line: &[u8], _line_number: usize, _issues: &mut Vec<ValidationIssue>) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+
+    fn clone_box(&self) -> Box<dyn Validator> {
+        Box::new(self.clone())
+    }
+}
+
+fn input() -> Vec<u8> {
+    b"a,b,c\n".repeat(200_000)
+}
+
+fn run(options: ValidationOptions) -> (Error, Duration) {
+    let started = Instant::now();
+    let options = ValidationOptions { threads: 2, batch_size: 1_000, ..options };
+    let error = validate_reader(&input()[..], Arc::new(vec![Box::new(SlowValidator) as Box<dyn Validator>]), options)
+        .expect_err("validation should have been interrupted");
+    (error, started.elapsed())
+}
+
+#[test]
+fn cancelled_before_the_start() {
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let (error, _) = run(ValidationOptions { cancellation: Some(token), ..Default::default() });
+    assert!(matches!(error, Error::Cancelled), "{:?}", error);
+}
+
+#[test]
+fn cancelled_while_running() {
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        canceller.cancel();
+    });
+
+    let (error, elapsed) = run(ValidationOptions { cancellation: Some(token), ..Default::default() });
+    assert!(matches!(error, Error::Cancelled), "{:?}", error);
+    assert!(elapsed < Duration::from_secs(10), "took {:?}", elapsed);
+}
+
+#[test]
+fn timeout_stops_the_validation() {
+    let (error, elapsed) = run(ValidationOptions { timeout: Some(Duration::from_millis(100)), ..Default::default() });
+    assert!(matches!(error, Error::TimedOut(timeout) if timeout == Duration::from_millis(100)), "{:?}", error);
+    assert!(elapsed < Duration::from_secs(10), "took {:?}", elapsed);
+}
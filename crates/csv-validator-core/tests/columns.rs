@@ -0,0 +1,168 @@
+use csv_validator_core::{Column, ColumnType, Dialect, IssueCode, Severity, ValidatorSpec, ValidationOptions, into_validators, validate_reader};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+mod common;
+use common::temp_dir;
+
+fn issues(spec: ValidatorSpec, data: &[u8]) -> Vec<(usize, IssueCode, String)> {
+    let dialect = Dialect { has_header: true, ..Dialect::default() };
+    let validators = Arc::new(into_validators([spec], &dialect).unwrap());
+    let options = ValidationOptions { has_header: true, ..Default::default() };
+    validate_reader(data, validators, options)
+        .expect("validation failed")
+        .issues
+        .into_iter()
+        .map(|issue| (issue.line_number, issue.code, issue.message))
+        .collect()
+}
+
+#[test]
+fn values_are_checked_against_their_column() {
+    let columns = vec![
+        Column { required: true, ..Column::new("id", ColumnType::Integer) },
+        Column { max_length: Some(5), pattern: Some("[a-z]+".to_string()), ..Column::new("name", ColumnType::String) },
+        Column { minimum: Some("0".to_string()), maximum: Some("1.5".to_string()), ..Column::new("score", ColumnType::Number) },
+        Column { allowed: Some(vec!["a".to_string(), "b".to_string()]), ..Column::new("grade", ColumnType::String) },
+        Column::new("born", ColumnType::Date),
+    ];
+    let data = b"id,name,score,grade,born\n1,ann,1.5,a,2024-02-29\n,\"bo\"\"b\",2,c,2023-02-29\nx,abcdef,-1e3,,\n";
+
+    assert_eq!(
+        issues(ValidatorSpec::new_columns(columns), data),
+        vec![
+            (3, IssueCode::REQUIRED_VALUE_MISSING, "Column 'id' is required, but has no value".to_string()),
+            (3, IssueCode::PATTERN_MISMATCH, "Column 'name': 'bo\"b' doesn't match the pattern [a-z]+".to_string()),
+            (3, IssueCode::OUT_OF_RANGE, "Column 'score': 2 is above the maximum 1.5".to_string()),
+            (3, IssueCode::NOT_IN_ENUM, "Column 'grade': 'c' is not one of a, b".to_string()),
+            (3, IssueCode::TYPE_MISMATCH, "Column 'born': '2023-02-29' is not a valid date".to_string()),
+            (4, IssueCode::TYPE_MISMATCH, "Column 'id': 'x' is not a valid integer".to_string()),
+            (4, IssueCode::LENGTH_OUT_OF_RANGE, "Column 'name': 'abcdef' is 6 characters long, expected at most 5".to_string()),
+            (4, IssueCode::OUT_OF_RANGE, "Column 'score': -1e3 is below the minimum 0".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn header_and_keys_are_checked() {
+    let spec = ValidatorSpec::Columns {
+        id: None,
+        columns: vec![Column::new("a", ColumnType::String), Column::new("b", ColumnType::Integer)],
+        missing_values: vec!["NA".to_string()],
+        unique_keys: vec![vec!["a".to_string(), "b".to_string()]],
+        foreign_keys: Vec::new(),
+        enabled: true,
+        severity: Severity::Error,
+        common: None,
+    };
+    let data = b"a,c\nx,1\ny,1\nx,1\nx,NA\nx,NA\n";
+
+    assert_eq!(
+        issues(spec, data),
+        vec![
+            (1, IssueCode::HEADER_MISMATCH, "Header column 'c' should be 'b'".to_string()),
+            (4, IssueCode::NOT_UNIQUE, "Column(s) a, b: 'x, 1' is not unique, also on line 2".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn invalid_columns_are_config_errors() {
+    let bad_pattern = Column { pattern: Some("(".to_string()), ..Column::new("a", ColumnType::String) };
+    let bad_minimum = Column { minimum: Some("soon".to_string()), ..Column::new("b", ColumnType::Date) };
+    let string_maximum = Column { maximum: Some("z".to_string()), ..Column::new("c", ColumnType::String) };
+    for (column, expected) in [
+        (bad_pattern, "column 'a': invalid pattern"),
+        (bad_minimum, "column 'b': 'soon' is not a date value"),
+        (string_maximum, "column 'c': minimum and maximum are for numbers, dates and times, not string values"),
+    ] {
+        let error = ValidatorSpec::new_columns(vec![column]).into_validator(b',').err().unwrap().to_string();
+        assert!(error.contains(expected), "{}", error);
+    }
+}
+
+#[test]
+fn skipped_rows_come_before_the_header() {
+    let columns = vec![
+        Column { titles: vec!["ID".to_string()], ..Column::new("id", ColumnType::Integer) },
+        Column {
+            titles: vec!["Name".to_string()],
+            required: true,
+            missing_values: Some(vec!["NA".to_string()]),
+            ..Column::new("name", ColumnType::String)
+        },
+    ];
+    let dialect = Dialect { has_header: true, skip_rows: 1, ..Dialect::default() };
+    let validators = Arc::new(into_validators([ValidatorSpec::new_columns(columns)], &dialect).unwrap());
+    let options = ValidationOptions { has_header: true, skip_rows: 1, ..Default::default() };
+
+    let outcome = validate_reader(&b"Source: a survey\nID,Name\n1,a\nx,NA\n"[..], validators, options).expect("validation failed");
+    let issues: Vec<_> = outcome.issues.iter().map(|issue| (issue.line_number, issue.code, issue.message.as_str())).collect();
+
+    assert_eq!(
+        issues,
+        vec![
+            (4, IssueCode::TYPE_MISMATCH, "Column 'id': 'x' is not a valid integer"),
+            (4, IssueCode::REQUIRED_VALUE_MISSING, "Column 'name' is required, but has no value"),
+        ]
+    );
+    assert_eq!(outcome.report.records, 2);
+    assert_eq!(outcome.report.columns, vec!["ID", "Name"]);
+}
+
+#[test]
+fn unique_keys_are_checked_in_line_order() {
+    let dir = temp_dir("unique");
+    let rejects = dir.join("rejects.csv");
+
+    let columns = vec![Column { unique: true, ..Column::new("id", ColumnType::Integer) }, Column::new("name", ColumnType::String)];
+    let dialect = Dialect { has_header: true, ..Dialect::default() };
+    let validators = Arc::new(into_validators([ValidatorSpec::new_columns(columns)], &dialect).unwrap());
+    let mut data = String::from("id,name\n");
+    for i in 0..200 {
+        data.push_str(&format!("{},n{}\n", i % 50, i));
+    }
+    // each id is first on one of the lines 2 to 51
+    let duplicate = |i: usize| format!("Column(s) id: '{}' is not unique, also on line {}", i % 50, i % 50 + 2);
+    let expected: Vec<_> = (50..200).map(|i| (i + 2, IssueCode::NOT_UNIQUE, duplicate(i))).collect();
+    let mut expected_rejects = String::from("id,name,line_number,issues\n");
+    for i in 50..200 {
+        expected_rejects.push_str(&format!("{},n{},{},\"{}\"\n", i % 50, i, i + 2, duplicate(i)));
+    }
+
+    // small batches on many threads, unordered, and the same validators twice: the first line keeps the values
+    for _ in 0..2 {
+        let options = ValidationOptions {
+            threads: 8,
+            batch_size: 3,
+            has_header: true,
+            preserve_order: false,
+            reject_output: Some(rejects.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let outcome = validate_reader(data.as_bytes(), validators.clone(), options).expect("validation failed");
+        let found: Vec<_> = outcome.issues.into_iter().map(|issue| (issue.line_number, issue.code, issue.message)).collect();
+        assert_eq!(found, expected);
+        assert_eq!(outcome.report.records_with_issues, 150);
+        assert_eq!(std::fs::read_to_string(&rejects).unwrap(), expected_rejects);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn crlf_line_ends_are_not_part_of_the_last_column() {
+    let columns = vec![
+        Column::new("name", ColumnType::String),
+        Column { unique: true, titles: vec!["Age".to_string()], ..Column::new("age", ColumnType::Integer) },
+    ];
+    let data = b"name,Age\r\nann,30\r\nbob,x\r\ncid,30\r\n";
+
+    assert_eq!(
+        issues(ValidatorSpec::new_columns(columns), data),
+        vec![
+            (3, IssueCode::TYPE_MISMATCH, "Column 'age': 'x' is not a valid integer".to_string()),
+            (4, IssueCode::NOT_UNIQUE, "Column(s) age: '30' is not unique, also on line 2".to_string()),
+        ]
+    );
+}
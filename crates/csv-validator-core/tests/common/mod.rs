@@ -0,0 +1,19 @@
+// each test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+/// An empty directory for the files of one test, named after it.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("csv-validator-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The path of `file` in `dir`, as validate_file takes it.
+pub fn path(dir: &Path, file: &str) -> String {
+    dir.join(file).to_str().unwrap().to_string()
+}
@@ -0,0 +1,60 @@
+use csv_validator_core::{CommonSettings, Dialect, Error, ValidatorSpec};
+use pretty_assertions::assert_eq;
+
+fn settings(separator: Option<char>, quote_char: Option<char>, has_header: Option<bool>) -> CommonSettings {
+    CommonSettings { separator, quote_char, has_header, skip_rows: None }
+}
+
+#[test]
+fn precedence_is_overrides_validator_top_level_default() {
+    let top = settings(Some(';'), Some('\''), None);
+    let specs = vec![
+        ValidatorSpec::new_field_count(3).with_common(settings(Some('|'), None, Some(true))),
+        ValidatorSpec::new_line_length(80).with_common(settings(Some('|'), None, None)),
+    ];
+
+    // the line_length spec inherits has_header from the default, which conflicts with the field_count spec
+    let error = Dialect::resolve(&CommonSettings::default(), &top, &specs).expect_err("has_header conflict");
+    assert_eq!(
+        error.to_string(),
+        "invalid configuration: validators read the same stream, their common settings conflict: \
+         has_header true for field_count (validator 1), but false for line_length (validator 2)"
+    );
+
+    let overrides = settings(None, None, Some(true));
+    assert_eq!(
+        Dialect::resolve(&overrides, &top, &specs).unwrap(),
+        Dialect { separator: b'|', quote_char: b'\'', has_header: true, skip_rows: 0 }
+    );
+
+    let overrides = settings(Some('\t'), Some('"'), Some(false));
+    assert_eq!(
+        Dialect::resolve(&overrides, &top, &specs).unwrap(),
+        Dialect { separator: b'\t', quote_char: b'"', has_header: false, skip_rows: 0 }
+    );
+
+    assert_eq!(Dialect::resolve(&CommonSettings::default(), &CommonSettings::default(), &[]).unwrap(), Dialect::default());
+}
+
+#[test]
+fn disabled_validators_dont_conflict() {
+    let yaml = r#"
+- type: field_count
+  expected: 3
+  common: {separator: ';'}
+- type: illegal_chars
+  illegal_chars: ['@']
+  enabled: false
+  common: {separator: ','}
+"#;
+    let specs: Vec<ValidatorSpec> = serde_yaml::from_str(yaml).unwrap();
+
+    let dialect = Dialect::resolve(&CommonSettings::default(), &settings(Some(','), None, None), &specs).unwrap();
+    assert_eq!(dialect.separator, b';');
+}
+
+#[test]
+fn separators_must_be_ascii() {
+    let error = Dialect::resolve(&settings(Some('§'), None, None), &CommonSettings::default(), &[]).expect_err("not ASCII");
+    assert!(matches!(error, Error::Config(_)), "{:?}", error);
+}
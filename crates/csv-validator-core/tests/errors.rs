@@ -0,0 +1,56 @@
+use csv_validator_core::{
+    BufferedLineReader, Error, FieldCountRepair, FieldCountValidator, IllegalCharactersValidator, LineLengthValidator, MergeColumn,
+    MemoryBufferedReader, ValidatorSpec, ValidationOptions, validate_file, validate_reader,
+};
+use std::sync::Arc;
+
+#[test]
+fn bad_validator_settings_are_config_errors() {
+    let errors = [
+        IllegalCharactersValidator::new(&["@", ""]).err(),
+        IllegalCharactersValidator::with_replacements(&["@", "!"], &["_"]).err(),
+        FieldCountValidator::new(0, b',').err(),
+        FieldCountValidator::with_repair(3, b',', FieldCountRepair::Merge(MergeColumn::Index(3))).err(),
+        LineLengthValidator::new(0).err(),
+        ValidatorSpec::new_illegal_chars(vec![String::new()]).into_validator(b',').err(),
+    ];
+
+    for error in errors {
+        assert!(matches!(error, Some(Error::Config(_))), "{:?}", error);
+    }
+    assert_eq!(
+        LineLengthValidator::new(0).err().map(|e| e.to_string()),
+        Some("invalid configuration: the maximum line length must be at least 1".to_string())
+    );
+}
+
+#[test]
+fn conflicting_outputs_are_config_errors() {
+    let validators = Arc::new(vec![ValidatorSpec::new_field_count(3).into_validator(b',').unwrap()]);
+    let options = ValidationOptions {
+        fix_output: Some("fixed.csv".to_string()),
+        clean_output: Some("clean.csv".to_string()),
+        ..Default::default()
+    };
+
+    let error = validate_reader(&b"a,b,c"[..], validators, options).expect_err("outputs conflict");
+    assert!(matches!(error, Error::Config(_)), "{:?}", error);
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    let validators = Arc::new(vec![ValidatorSpec::new_field_count(3).into_validator(b',').unwrap()]);
+
+    let error = validate_file("tests/data/missing.csv", validators, ValidationOptions::default()).expect_err("no such file");
+    assert!(matches!(&error, Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound), "{:?}", error);
+}
+
+#[test]
+fn text_lines_that_are_not_utf8_are_encoding_errors() {
+    let mut reader = MemoryBufferedReader::new(b"ok\nn\xffpe\n", 16);
+    let mut line = String::new();
+
+    assert!(reader.next_line(&mut line).unwrap());
+    let error = reader.next_line(&mut line).expect_err("invalid UTF-8");
+    assert!(matches!(error, Error::Encoding { line: 2 }), "{:?}", error);
+}
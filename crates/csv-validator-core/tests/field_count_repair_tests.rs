@@ -0,0 +1,121 @@
+use csv_validator_core::{
+    FieldCountRepair, FieldCountValidator, IssueStatus, MergeColumn, ValidationOptions, ValidationResult, Validator, ValidatorSpec,
+    validate_reader,
+};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+mod common;
+use common::temp_dir;
+
+fn fix(validator: &FieldCountValidator, line: &[u8]) -> (Vec<u8>, bool, Vec<IssueStatus>) {
+    let mut issues = Vec::new();
+    let result = validator.fix(ValidationResult::new(line), 1, &mut issues);
+    (result.line.into_owned(), result.rejected, issues.iter().map(|i| i.status).collect())
+}
+
+#[test]
+fn pad_adds_empty_fields() {
+    let validator = FieldCountValidator::with_repair(4, b';', FieldCountRepair::Pad).unwrap();
+
+    assert_eq!(fix(&validator, b"a;b"), (b"a;b;;".to_vec(), false, vec![IssueStatus::Fixed]));
+    // too many fields can't be padded
+    assert_eq!(fix(&validator, b"a;b;c;d;e"), (b"a;b;c;d;e".to_vec(), false, vec![IssueStatus::Open]));
+}
+
+#[test]
+fn truncate_drops_extra_fields() {
+    let validator = FieldCountValidator::with_repair(2, b';', FieldCountRepair::Truncate).unwrap();
+
+    assert_eq!(fix(&validator, b"a;b;c;d"), (b"a;b".to_vec(), false, vec![IssueStatus::Fixed]));
+    assert_eq!(fix(&validator, b"a;b"), (b"a;b".to_vec(), false, vec![]));
+}
+
+#[test]
+fn merge_quotes_overflow_into_column() {
+    let validator = FieldCountValidator::with_repair(3, b';', FieldCountRepair::Merge(MergeColumn::Index(1))).unwrap();
+
+    assert_eq!(
+        fix(&validator, br#"1;hello;"big";world;x"#),
+        (br#"1;"hello;big;world";x"#.to_vec(), false, vec![IssueStatus::Fixed])
+    );
+}
+
+#[test]
+fn merge_into_named_column() {
+    let repair = FieldCountRepair::merge_into(b"id;\"comment\";amount", b';', b'"', "comment");
+    assert_eq!(repair, Some(FieldCountRepair::Merge(MergeColumn::Index(1))));
+    let repair = FieldCountRepair::merge_into(b"id;'a;b';'comment'\r", b';', b'\'', "comment");
+    assert_eq!(repair, Some(FieldCountRepair::Merge(MergeColumn::Index(2))));
+    assert_eq!(FieldCountRepair::merge_into(b"id;comment", b';', b'"', "missing"), None);
+}
+
+#[test]
+fn engine_looks_up_named_merge_column_in_header() {
+    let dir = temp_dir("merge");
+    let output = dir.join("fixed.csv");
+
+    let spec = ValidatorSpec::new_field_count_with_repair(3, "merge:comment".parse().unwrap());
+    let validators = Arc::new(vec![spec.into_validator(b';').unwrap()]);
+    let options = ValidationOptions {
+        separator: b';',
+        has_header: true,
+        skip_rows: 1,
+        fix_output: Some(output.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    // the skipped line isn't the header
+    let data = b"comment;exported\nid;comment;amount\n1;a;b;2\n";
+    validate_reader(&data[..], validators.clone(), options).expect("validation failed");
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "comment;exported\nid;comment;amount\n1;\"a;b\";2\n");
+
+    let error = validate_reader(&data[..], validators, ValidationOptions { separator: b';', ..Default::default() }).err().unwrap();
+    assert!(error.to_string().contains("can't merge into column 'comment' without a header"), "{}", error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reject_marks_line_rejected() {
+    let validator = FieldCountValidator::with_repair(3, b';', FieldCountRepair::Reject).unwrap();
+
+    assert_eq!(fix(&validator, b"a;b"), (b"a;b".to_vec(), true, vec![IssueStatus::Rejected]));
+}
+
+#[test]
+fn parse_repair() {
+    assert_eq!("pad".parse(), Ok(FieldCountRepair::Pad));
+    assert_eq!("merge:2".parse(), Ok(FieldCountRepair::Merge(MergeColumn::Index(2))));
+    assert_eq!("merge:comment".parse(), Ok(FieldCountRepair::Merge(MergeColumn::Name("comment".to_string()))));
+    assert!("merge:".parse::<FieldCountRepair>().is_err());
+}
+
+#[test]
+fn delimiters_inside_quotes_are_not_fields() {
+    let validator = FieldCountValidator::with_repair(3, b';', FieldCountRepair::Truncate).unwrap();
+
+    assert_eq!(fix(&validator, br#"1;"a;b;c";3"#), (br#"1;"a;b;c";3"#.to_vec(), false, vec![]));
+    assert_eq!(fix(&validator, br#"1;"a;b";3;4"#), (br#"1;"a;b";3"#.to_vec(), false, vec![IssueStatus::Fixed]));
+}
+
+#[test]
+fn merge_keeps_escaped_quotes() {
+    let validator = FieldCountValidator::with_repair(2, b';', FieldCountRepair::Merge(MergeColumn::Index(1))).unwrap();
+
+    assert_eq!(
+        fix(&validator, br#"1;"say ""hi""";b;y"#),
+        (br#"1;"say ""hi"";b;y""#.to_vec(), false, vec![IssueStatus::Fixed])
+    );
+}
+
+#[test]
+fn repairs_keep_the_crlf_line_end() {
+    let pad = FieldCountValidator::with_repair(4, b',', FieldCountRepair::Pad).unwrap();
+    assert_eq!(fix(&pad, b"a,b\r"), (b"a,b,,\r".to_vec(), false, vec![IssueStatus::Fixed]));
+
+    let truncate = FieldCountValidator::with_repair(2, b',', FieldCountRepair::Truncate).unwrap();
+    assert_eq!(fix(&truncate, b"a,b,c\r"), (b"a,b\r".to_vec(), false, vec![IssueStatus::Fixed]));
+
+    let merge = FieldCountValidator::with_repair(2, b',', FieldCountRepair::Merge(MergeColumn::Index(1))).unwrap();
+    assert_eq!(fix(&merge, b"1,a,b\r"), (b"1,\"a,b\"\r".to_vec(), false, vec![IssueStatus::Fixed]));
+}
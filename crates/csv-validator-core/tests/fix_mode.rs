@@ -0,0 +1,81 @@
+use csv_validator_core::{FieldCountRepair, IssueStatus, ValidatorSpec, ValidationOptions, validate_file};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn fix_mode_writes_corrected_file_in_original_order() {
+    let dir = temp_dir("fix");
+    let input = dir.join("input.csv");
+    let output = dir.join("fixed.csv");
+
+    let mut data = String::from("id;name\n");
+    for i in 1..=1000 {
+        data.push_str(&format!("{};name@{}!\n", i, i));
+    }
+    std::fs::write(&input, &data).unwrap();
+
+    let specs = vec![
+        ValidatorSpec::new_illegal_chars_with_replacements(vec!["@".to_string()], vec!["_".to_string()]),
+        ValidatorSpec::new_illegal_chars_with_replacements(vec!["_".to_string(), "!".to_string()], vec!["-".to_string(), "".to_string()]),
+        ValidatorSpec::new_field_count(2),
+    ];
+    let validators = Arc::new(specs.into_iter().map(|s| s.into_validator(b';').unwrap()).collect::<Vec<_>>());
+
+    let options = ValidationOptions {
+        threads: 4,
+        batch_size: 7,
+        fix_output: Some(output.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+
+    let issues = validate_file(input.to_str().unwrap(), validators, options).expect("validation failed").issues;
+
+    // the second validator sees the output of the first one
+    assert_eq!(issues.len(), 3 * 1000);
+
+    let fixed = std::fs::read_to_string(&output).unwrap();
+    let mut expected = String::from("id;name\n");
+    for i in 1..=1000 {
+        expected.push_str(&format!("{};name-{}\n", i, i));
+    }
+    assert_eq!(fixed, expected);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fix_mode_sends_rejected_lines_to_reject_file() {
+    let dir = temp_dir("reject");
+    let input = dir.join("input.csv");
+    let output = dir.join("fixed.csv");
+    let rejects = dir.join("rejects.csv");
+
+    std::fs::write(&input, "a;b;c\n1;2;3\n1;2\n1;2;3;4\n4;5;6\n").unwrap();
+
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_field_count_with_repair(3, FieldCountRepair::Reject).into_validator(b';').unwrap(),
+    ]);
+
+    let options = ValidationOptions {
+        batch_size: 2,
+        separator: b';',
+        fix_output: Some(output.to_str().unwrap().to_string()),
+        reject_output: Some(rejects.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+
+    let issues = validate_file(input.to_str().unwrap(), validators, options).expect("validation failed").issues;
+
+    assert_eq!(issues.len(), 2);
+    assert!(issues.iter().all(|i| i.status == IssueStatus::Rejected));
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "a;b;c\n1;2;3\n4;5;6\n");
+    assert_eq!(
+        std::fs::read_to_string(&rejects).unwrap(),
+        "1;2;3;\"Expected 3 fields, found 2, rejected\"\n1;2;3;4;4;\"Expected 3 fields, found 4, rejected\"\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,49 @@
+use csv_validator_core::{
+    FieldCountValidator, FusedScanner, IllegalCharactersValidator, LineLengthValidator, ScanBuffers, ValidationIssue,
+    Validator,
+};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+fn summary(issues: &[ValidationIssue]) -> Vec<(&'static str, Option<usize>, String)> {
+    issues.iter().map(|i| (i.validator, i.position, i.message.clone())).collect()
+}
+
+#[test]
+fn fused_scan_reports_the_same_issues_as_each_validator_alone() {
+    // overlapping patterns, within and across validators
+    let validators: Arc<Vec<Box<dyn Validator>>> = Arc::new(vec![
+        Box::new(IllegalCharactersValidator::new(&["ab", "b"]).unwrap()),
+        Box::new(IllegalCharactersValidator::new(&["bc", "abc", "c"]).unwrap()),
+        Box::new(FieldCountValidator::new(3, b',').unwrap()),
+        Box::new(LineLengthValidator::new(12).unwrap()),
+        Box::new(FieldCountValidator::new(2, b';').unwrap()),
+        Box::new(FieldCountValidator::new(4, b',').unwrap()),
+        Box::new(IllegalCharactersValidator::new(&["aa"]).unwrap()),
+    ]);
+    let scanner = FusedScanner::new(validators.clone()).unwrap();
+    let mut buffers = ScanBuffers::default();
+
+    let lines: [&[u8]; 6] = [b"abc,abcabc,c", b"\"a,b\",c;d,e", b"aaaa,b", b"", b"x,y,z", b"bbb;ccc,\"abc"];
+    for (i, line) in lines.iter().enumerate() {
+        let mut alone = Vec::new();
+        for validator in validators.iter() {
+            validator.validate(line, i + 1, &mut alone);
+        }
+        let mut fused = Vec::new();
+        scanner.validate(line, i + 1, &mut buffers, &mut fused);
+
+        assert_eq!(summary(&fused), summary(&alone), "line {}", String::from_utf8_lossy(line));
+    }
+}
+
+#[test]
+fn fused_scan_without_patterns_or_fields() {
+    let validators: Arc<Vec<Box<dyn Validator>>> = Arc::new(vec![Box::new(LineLengthValidator::new(3).unwrap())]);
+    let scanner = FusedScanner::new(validators).unwrap();
+    let mut issues = Vec::new();
+    scanner.validate(b"abcd", 7, &mut ScanBuffers::default(), &mut issues);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].line_number, 7);
+}
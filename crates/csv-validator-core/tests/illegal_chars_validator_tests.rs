@@ -1,51 +1,50 @@
-use csv_validator_core::{IllegalCharacterValidator, Validator, issue::ValidationContext, IllegalCharactersValidator};
+use csv_validator_core::{IllegalCharactersValidator, ValidationResult, Validator};
 use pretty_assertions::assert_eq;
 
 #[test]
 fn illegal_character_validator_check_only_mode() {
-    let validator = IllegalCharactersValidator::new( &["_"]);
-    let mut context = ValidationContext::new();
+    let validator = IllegalCharactersValidator::new( &["@"]).unwrap();
+    let mut issues = Vec::new();
 
-    let input = "hello@world";
-    let result = validator.validate(input, &mut context, 1, false);
+    let input = b"hello@world";
+    let result = validator.fix(ValidationResult::new(input), 1, &mut issues);
 
-    assert_eq!(context.issues.len(), 1);
-    assert_eq!(result.line, "hello@world");
-    assert!(!result.modified);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(result.line.as_ref(), b"hello@world");
+    assert!(!result.is_modified());
 }
 
 #[test]
 fn illegal_character_validator_fix_mode() {
-    let validator = IllegalCharactersValidator::new( &["_"]);
-    let mut context = ValidationContext::new();
+    let validator = IllegalCharactersValidator::with_replacements( &["@"], &["_"]).unwrap();
+    let mut issues = Vec::new();
 
-    let input = "hello@world";
-    let result = validator.validate(input, &mut context, 1, true);
+    let input = b"hello@world";
+    let result = validator.fix(ValidationResult::new(input), 1, &mut issues);
 
-    assert_eq!(context.issues.len(), 1);
-    assert_eq!(result.line, "hello_world");
-    assert!(result.modified);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(result.line.as_ref(), b"hello_world");
+    assert!(result.is_modified());
 }
 
 #[test]
 fn validator_chaining_example() {
-    let illegal_char_validator = IllegalCharactersValidator::new( &["_"]);
-    let another_illegal_validator = IllegalCharactersValidator::new(&["!"], &["."]);
+    let illegal_char_validator = IllegalCharactersValidator::with_replacements( &["@"], &["_"]).unwrap();
+    let another_illegal_validator = IllegalCharactersValidator::with_replacements(&["!"], &["."]).unwrap();
     let validators: Vec<&dyn Validator> = vec![&illegal_char_validator, &another_illegal_validator];
 
-    let mut context = ValidationContext::new();
+    let mut issues = Vec::new();
     let line_number = 1;
-    let fix = true;
 
-    let original_line = "hello@world!";
+    let original_line = b"hello@world!";
 
     // Start chaining
     let mut result = ValidationResult::new(original_line);
 
     for validator in validators {
-        result = validator.validate(result, &mut context, line_number, fix);
+        result = validator.fix(result, line_number, &mut issues);
     }
 
-    assert_eq!(result.line, "hello_world.");
-    assert_eq!(context.issues.len(), 2);
+    assert_eq!(result.line.as_ref(), b"hello_world.");
+    assert_eq!(issues.len(), 2);
 }
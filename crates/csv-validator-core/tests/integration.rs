@@ -1,8 +1,10 @@
 use csv_validator_core::{
-    ValidatorSpec, ValidationOptions, validate_file,
+    Dialect, Severity, ValidatorSpec, ValidationIssue, ValidationOptions, validate_file, validate_file_with_sink,
 };
 use std::sync::Arc;
-use std::path::Path;
+
+mod common;
+use common::temp_dir;
 
 #[test]
 fn test_validate_bad_csv_file() {
@@ -15,14 +17,17 @@ fn test_validate_bad_csv_file() {
         ]),
         ValidatorSpec::new_field_count(3),
         ValidatorSpec::LineLength {
+            id: None,
             enabled: true,
             max_length: 80,
+            severity: Severity::Error,
+            common: None,
         },
     ];
 
     let validators = Arc::new(
         specs.into_iter()
-            .map(|s| s.into_validator(b','))
+            .map(|s| s.into_validator(b',').unwrap())
             .collect::<Vec<_>>(),
     );
 
@@ -31,9 +36,10 @@ fn test_validate_bad_csv_file() {
         batch_size: 100,
         buffer_size: 1024 * 1024,
         preserve_order: false,
+        ..Default::default()
     };
 
-    let issues = validate_file(path, validators, options).expect("validation failed");
+    let issues = validate_file(path, validators, options).expect("validation failed").issues;
 
     assert!(!issues.is_empty(), "Expected validation issues");
 
@@ -47,3 +53,172 @@ fn test_validate_bad_csv_file() {
     // Example: assert that line 5 contains illegal Zzzzz
     assert!(issues.iter().any(|i| i.line_number == 5 && i.message.contains("Zzzzz")));
 }
+
+#[test]
+fn test_preserve_order_sorts_by_line_then_validator() {
+    let path = "tests/data/bad.csv";
+
+    let run = || {
+        let validators = Arc::new(vec![
+            ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+            ValidatorSpec::new_illegal_chars(vec!["@@".to_string(), "Zzzzz".to_string(), "o".to_string()])
+                .into_validator(b',').unwrap(),
+        ]);
+        let options = ValidationOptions {
+            threads: 4,
+            batch_size: 1,
+            preserve_order: true,
+            ..Default::default()
+        };
+        validate_file(path, validators, options)
+            .expect("validation failed")
+            .issues
+            .into_iter()
+            .map(|i| (i.line_number, i.validator, i.position))
+            .collect::<Vec<_>>()
+    };
+
+    let issues = run();
+    let mut sorted = issues.clone();
+    // field_count is declared first, so it sorts before illegal_characters on the same line
+    sorted.sort_by_key(|(line, validator, position)| (*line, *validator != "field_count", *position));
+
+    assert_eq!(issues, sorted);
+    for _ in 0..5 {
+        assert_eq!(run(), issues);
+    }
+}
+
+#[test]
+fn test_sink_receives_issues_batch_by_batch() {
+    let path = "tests/data/bad.csv";
+    let validators = || {
+        Arc::new(vec![
+            ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+            ValidatorSpec::new_illegal_chars(vec!["@@".to_string(), "Zzzzz".to_string()]).into_validator(b',').unwrap(),
+        ])
+    };
+    let options = || ValidationOptions {
+        threads: 2,
+        batch_size: 2,
+        ..Default::default()
+    };
+
+    let mut batches = 0;
+    let mut streamed = Vec::new();
+    let mut sink = |issues: Vec<ValidationIssue>| -> std::io::Result<()> {
+        batches += 1;
+        streamed.extend(issues.into_iter().map(|i| (i.line_number, i.message)));
+        Ok(())
+    };
+    validate_file_with_sink(path, validators(), options(), &mut sink).expect("validation failed");
+
+    let collected: Vec<_> = validate_file(path, validators(), options())
+        .expect("validation failed")
+        .issues
+        .into_iter()
+        .map(|i| (i.line_number, i.message))
+        .collect();
+
+    assert!(batches > 1);
+    assert_eq!(streamed, collected);
+}
+
+#[test]
+fn test_sink_error_stops_validation() {
+    let validators = Arc::new(vec![ValidatorSpec::new_field_count(3).into_validator(b',').unwrap()]);
+    let mut sink = |_: Vec<ValidationIssue>| -> std::io::Result<()> { Err(std::io::Error::other("sink full")) };
+
+    let options = ValidationOptions {
+        batch_size: 1,
+        ..Default::default()
+    };
+    let result = validate_file_with_sink("tests/data/bad.csv", validators, options, &mut sink);
+
+    assert_eq!(result.unwrap_err().to_string(), "sink full");
+}
+
+#[test]
+fn test_bounded_in_flight_batches_give_the_same_report() {
+    let dir = temp_dir("in-flight");
+    let input = dir.join("input.csv");
+
+    let mut data = String::new();
+    for i in 0..10_000 {
+        data.push_str(&format!("{},{},{}\n", i, if i % 3 == 0 { "x@@" } else { "x" }, "y"));
+    }
+    std::fs::write(&input, &data).unwrap();
+
+    let run = |max_in_flight| {
+        let validators = Arc::new(vec![
+            ValidatorSpec::new_illegal_chars(vec!["@@".to_string()]).into_validator(b',').unwrap(),
+        ]);
+        let options = ValidationOptions {
+            threads: 4,
+            batch_size: 10,
+            max_in_flight,
+            ..Default::default()
+        };
+        validate_file(input.to_str().unwrap(), validators, options)
+            .expect("validation failed")
+            .issues
+            .into_iter()
+            .map(|i| i.line_number)
+            .collect::<Vec<_>>()
+    };
+
+    let unbounded = run(Some(usize::MAX));
+    assert_eq!(unbounded.len(), 3334);
+    assert_eq!(run(Some(1)), unbounded);
+    assert_eq!(run(None), unbounded);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mmap_splitting_gives_the_same_report() {
+    let path = "tests/data/bad.csv";
+    let run = |mmap| {
+        let validators = Arc::new(vec![
+            ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+            ValidatorSpec::new_illegal_chars(vec!["@@".to_string(), "Zzzzz".to_string()]).into_validator(b',').unwrap(),
+        ]);
+        let options = ValidationOptions {
+            threads: 3,
+            batch_size: 2,
+            buffer_size: 16,
+            mmap,
+            ..Default::default()
+        };
+        validate_file(path, validators, options)
+            .expect("validation failed")
+            .issues
+            .into_iter()
+            .map(|i| (i.line_number, i.message))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn test_quote_char_applies_to_records_and_fields() {
+    let data = b"id;name\n1;'a;b'\n2;'multi\nline'\n3;c;d\n";
+    let validators = || {
+        Arc::new(csv_validator_core::into_validators([ValidatorSpec::new_field_count(2)], &Dialect { separator: b';', quote_char: b'\'', has_header: true, skip_rows: 0 }).unwrap())
+    };
+    let options = || ValidationOptions { separator: b';', quote_char: b'\'', has_header: true, ..Default::default() };
+
+    let outcome = csv_validator_core::validate_reader(&data[..], validators(), options()).expect("validation failed");
+    let lines: Vec<_> = outcome.issues.iter().map(|i| i.line_number).collect();
+    assert_eq!(lines, vec![4]);
+    assert_eq!(outcome.report.records, 3);
+
+    let dir = temp_dir("quote");
+    let input = dir.join("input.csv");
+    std::fs::write(&input, data).unwrap();
+    let mmap_options = ValidationOptions { mmap: true, buffer_size: 8, ..options() };
+    let issues = validate_file(input.to_str().unwrap(), validators(), mmap_options).expect("validation failed").issues;
+    assert_eq!(issues.iter().map(|i| i.line_number).collect::<Vec<_>>(), vec![4]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,70 @@
+use csv_validator_core::{IssueCode, IssueParam, Severity, ValidatorSpec, ValidationOptions, validate_file, validate_reader};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn issues_carry_code_severity_and_params() {
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+        ValidatorSpec::new_illegal_chars(vec!["@".to_string()]).with_severity(Severity::Warning).into_validator(b',').unwrap(),
+        ValidatorSpec::new_line_length(6).with_severity(Severity::Info).into_validator(b',').unwrap(),
+    ]);
+
+    let issues = validate_reader(&b"a,b,c\na,b\nab@,c,d"[..], validators, ValidationOptions::default())
+        .expect("validation failed")
+        .issues;
+    let found: Vec<_> = issues.iter().map(|i| (i.line_number, i.code, i.severity)).collect();
+
+    assert_eq!(
+        found,
+        vec![
+            (2, IssueCode::FIELD_COUNT_MISMATCH, Severity::Error),
+            (3, IssueCode::ILLEGAL_CHARACTER, Severity::Warning),
+            (3, IssueCode::LINE_TOO_LONG, Severity::Info),
+        ]
+    );
+    assert_eq!(issues[0].code.to_string(), "FC001 field_count_mismatch");
+    assert_eq!(issues[0].param("expected"), Some(&IssueParam::Number(3)));
+    assert_eq!(issues[0].param("found"), Some(&IssueParam::Number(2)));
+    assert_eq!(issues[1].param("found"), Some(&IssueParam::Text("@".to_string())));
+    assert_eq!(issues[2].param("max_length"), Some(&IssueParam::Number(6)));
+    assert_eq!(issues[2].param("length"), Some(&IssueParam::Number(7)));
+}
+
+#[test]
+fn only_errors_are_rejected() {
+    let dir = temp_dir("severity");
+    let clean = dir.join("clean.csv");
+    let rejects = dir.join("rejects.csv");
+
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+        ValidatorSpec::new_illegal_chars(vec!["@@".to_string()]).with_severity(Severity::Warning).into_validator(b',').unwrap(),
+    ]);
+    let options = ValidationOptions {
+        has_header: true,
+        clean_output: Some(clean.to_str().unwrap().to_string()),
+        reject_output: Some(rejects.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+
+    let report = validate_file("tests/data/bad.csv", validators, options).expect("validation failed").report;
+
+    assert_eq!(report.issues_per_severity, BTreeMap::from([(Severity::Warning, 1), (Severity::Error, 2)]));
+    assert_eq!(report.errors(), 2);
+    assert_eq!(
+        std::fs::read_to_string(&clean).unwrap(),
+        "id,name,comment\n1,alice,ok\n2,bob@@,email-like name\n4,Zzzzz,sleepy\n\
+         5,dave,this comment is far too long for the line length validator to accept without complaint\n7,frank,ok\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&rejects).unwrap(),
+        "id,name,comment,line_number,issues\n3,carol,too,many,fields,4,\"Expected 3 fields, found 5\"\n6,eve,7,\"Expected 3 fields, found 2\"\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
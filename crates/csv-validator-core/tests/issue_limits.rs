@@ -0,0 +1,64 @@
+use csv_validator_core::{ValidatorSpec, ValidationOptions, validate_reader};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+// every line has an illegal '@', every tenth line is too long as well
+fn input() -> Vec<u8> {
+    let mut input = Vec::new();
+    for i in 0..20_000 {
+        if i % 10 == 0 {
+            input.extend_from_slice(b"a@b,too long\n");
+        } else {
+            input.extend_from_slice(b"a@b,c\n");
+        }
+    }
+    input
+}
+
+fn run(options: ValidationOptions) -> (Vec<(&'static str, usize)>, bool) {
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_illegal_chars(vec!["@".to_string()]).into_validator(b',').unwrap(),
+        ValidatorSpec::new_line_length(10).into_validator(b',').unwrap(),
+    ]);
+    let options = ValidationOptions { threads: 4, batch_size: 100, max_in_flight: Some(4), ..options };
+    let outcome = validate_reader(&input()[..], validators, options).expect("validation failed");
+    // the report counts the issues reported, not those the limits left out
+    assert_eq!(outcome.report.total_issues(), outcome.issues.len() as u64);
+    let issues = outcome.issues.iter().map(|i| (i.validator, i.line_number)).collect();
+    (issues, outcome.report.truncated)
+}
+
+#[test]
+fn max_issues_keeps_the_first_issues() {
+    let (issues, truncated) = run(ValidationOptions { max_issues: Some(3), ..Default::default() });
+
+    assert_eq!(issues, vec![("illegal_characters", 1), ("line_length", 1), ("illegal_characters", 2)]);
+    assert!(truncated);
+}
+
+#[test]
+fn fail_fast_stops_at_the_first_issue() {
+    let (issues, truncated) = run(ValidationOptions { fail_fast: true, ..Default::default() });
+
+    assert_eq!(issues, vec![("illegal_characters", 1)]);
+    assert!(truncated);
+}
+
+#[test]
+fn max_issues_per_validator_caps_each_validator() {
+    let (issues, truncated) = run(ValidationOptions { max_issues_per_validator: Some(2), ..Default::default() });
+
+    assert_eq!(
+        issues,
+        vec![("illegal_characters", 1), ("line_length", 1), ("illegal_characters", 2), ("line_length", 11)]
+    );
+    assert!(truncated);
+}
+
+#[test]
+fn limits_not_reached_are_not_truncated() {
+    let (issues, truncated) = run(ValidationOptions { max_issues: Some(1_000_000), ..Default::default() });
+
+    assert_eq!(issues.len(), 22_000);
+    assert!(!truncated);
+}
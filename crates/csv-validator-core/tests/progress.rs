@@ -0,0 +1,50 @@
+use csv_validator_core::{Progress, ValidatorSpec, ValidationOptions, validate_file, validate_reader};
+use pretty_assertions::assert_eq;
+use std::sync::{Arc, Mutex};
+
+fn recording() -> (Arc<Mutex<Vec<Progress>>>, ValidationOptions) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorder = events.clone();
+    let options = ValidationOptions {
+        threads: 2,
+        batch_size: 2,
+        on_progress: Some(Arc::new(move |progress: &Progress| recorder.lock().unwrap().push(*progress))),
+        ..Default::default()
+    };
+    (events, options)
+}
+
+#[test]
+fn progress_ends_with_the_totals() {
+    let path = "tests/data/bad.csv";
+    let (events, options) = recording();
+    let validators = Arc::new(vec![ValidatorSpec::new_field_count(3).into_validator(b',').unwrap()]);
+
+    let issues = validate_file(path, validators, options).expect("validation failed").issues;
+
+    let events = events.lock().unwrap();
+    let last = events.last().expect("no progress reported");
+    let size = std::fs::metadata(path).unwrap().len();
+    assert!(last.finished);
+    assert_eq!(last.bytes, size);
+    assert_eq!(last.total_bytes, Some(size));
+    assert_eq!(last.records, 8);
+    assert_eq!(last.issues, issues.len() as u64);
+    assert_eq!(last.fraction(), Some(1.0));
+
+    assert!(events.windows(2).all(|pair| pair[0].records <= pair[1].records && pair[0].bytes <= pair[1].bytes));
+    assert_eq!(events.iter().filter(|event| event.finished).count(), 1);
+}
+
+#[test]
+fn streams_have_no_total() {
+    let (events, options) = recording();
+    let validators = Arc::new(vec![ValidatorSpec::new_line_length(3).into_validator(b',').unwrap()]);
+
+    validate_reader(&b"a\nbcde\nf"[..], validators, options).expect("validation failed");
+
+    let events = events.lock().unwrap();
+    let last = events.last().expect("no progress reported");
+    assert_eq!((last.bytes, last.records, last.issues, last.total_bytes), (8, 3, 1, None));
+    assert_eq!(last.fraction(), None);
+}
@@ -0,0 +1,171 @@
+use bytes::Bytes;
+use csv_validator_core::{ChunkedRecordReader, MmapRecordSplitter, OptimizedQuoteAwareReader, RecordBatchReader, StructuralIndexer};
+use pretty_assertions::assert_eq;
+use rayon::ThreadPoolBuilder;
+use std::sync::Arc;
+
+fn logical_lines(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut reader = OptimizedQuoteAwareReader::new(data, 1024);
+    let mut line_buf = Vec::new();
+    let mut lines = Vec::new();
+    while let Some(line) = reader.next_logical_line(&mut line_buf).unwrap() {
+        lines.push(line.to_vec());
+    }
+    lines
+}
+
+fn chunked_records(data: &[u8], chunk_size: usize, batch_size: usize) -> Vec<(usize, Vec<u8>)> {
+    records(ChunkedRecordReader::new(data, chunk_size), batch_size)
+}
+
+fn split_records(data: &[u8], chunk_size: usize, batch_size: usize) -> Vec<(usize, Vec<u8>)> {
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(3).build().unwrap());
+    records(MmapRecordSplitter::new(Bytes::copy_from_slice(data), chunk_size, pool), batch_size)
+}
+
+fn records<B: RecordBatchReader>(mut reader: B, batch_size: usize) -> Vec<(usize, Vec<u8>)> {
+    let mut records = Vec::new();
+    while let Some(batch) = reader.next_batch(batch_size).unwrap() {
+        assert!(batch.len() <= batch_size);
+        records.extend(batch.iter().map(|(line_number, record)| (line_number, record.to_vec())));
+    }
+    records
+}
+
+#[test]
+fn chunked_reader_splits_like_the_quote_aware_reader() {
+    let inputs: Vec<&[u8]> = vec![
+        b"a,b\n1,2\n3,4\n",
+        b"a,b\n1,2\n3,4",
+        b"a,\"multi\nline\",c\n\n\"x\"\"y\",z\n",
+        // batches end before and after the quoted newline of a scan they share
+        b"1,a\n2,\"b\nc\"\n3,\"d\"\n4,e\n",
+        b"\"unterminated,\nquote",
+        b"",
+        b"\n\n",
+    ];
+
+    for input in inputs {
+        let expected: Vec<(usize, Vec<u8>)> = logical_lines(input)
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .collect();
+
+        // chunks and batches cut everywhere, including inside quotes
+        for chunk_size in [1, 2, 3, 7, 1024] {
+            for batch_size in [1, 2, 100] {
+                assert_eq!(chunked_records(input, chunk_size, batch_size), expected);
+                assert_eq!(split_records(input, chunk_size, batch_size), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn chunked_reader_reads_the_test_files() {
+    for path in [
+        "../../tests/with_header.csv",
+        "../../tests/semi_quoted_with_header.csv",
+        "../../tests/full_quoted_with_header_semicolon.csv",
+    ] {
+        let data = std::fs::read(path).unwrap();
+        let expected: Vec<Vec<u8>> = logical_lines(&data);
+        let records: Vec<Vec<u8>> = chunked_records(&data, 16, 3).into_iter().map(|(_, r)| r).collect();
+        assert_eq!(records, expected, "{}", path);
+        let records: Vec<Vec<u8>> = split_records(&data, 16, 3).into_iter().map(|(_, r)| r).collect();
+        assert_eq!(records, expected, "{}", path);
+    }
+}
+
+#[test]
+fn batches_share_one_buffer() {
+    let mut reader = ChunkedRecordReader::new(&b"1,a\n2,b\n3,c\n4,d\n"[..], 1024);
+
+    let batch = reader.next_batch(3).unwrap().unwrap();
+    assert_eq!(batch.first_line_number, 1);
+    assert_eq!(batch.len(), 3);
+    assert_eq!(batch.record(2), b"3,c");
+    assert_eq!(&batch.data[..], b"1,a\n2,b\n3,c\n");
+
+    let batch = reader.next_batch(3).unwrap().unwrap();
+    assert_eq!(batch.first_line_number, 4);
+    assert_eq!(batch.record(0), b"4,d");
+
+    assert!(reader.next_batch(3).unwrap().is_none());
+}
+
+#[test]
+fn splitter_resolves_quotes_across_chunks() {
+    // quoted fields with newlines, spanning many tiny chunks
+    let mut data = Vec::new();
+    for i in 0..500 {
+        data.extend_from_slice(format!("{},\"a\nb\nc\",\"x\"\"y\"\n", i).as_bytes());
+    }
+    let expected: Vec<(usize, Vec<u8>)> = logical_lines(&data)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+    assert_eq!(expected.len(), 500);
+
+    for chunk_size in [1, 5, 64, 4096] {
+        assert_eq!(split_records(&data, chunk_size, 7), expected);
+    }
+}
+
+/// Byte at a time reference: delimiters and newlines outside of quotes.
+fn naive_structural(data: &[u8], delimiter: u8) -> (Vec<usize>, bool) {
+    let mut in_quotes = false;
+    let mut positions = Vec::new();
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'"' {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && (b == delimiter || b == b'\n') {
+            positions.push(i);
+        }
+    }
+    (positions, in_quotes)
+}
+
+#[test]
+fn structural_indexer_matches_the_byte_at_a_time_scan() {
+    // pseudo random mix of structural and plain bytes, across many block sizes and offsets
+    let alphabet = b"ab;\"\n,x";
+    let mut seed: u64 = 42;
+    let data: Vec<u8> = (0..10_000)
+        .map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            alphabet[(seed >> 33) as usize % alphabet.len()]
+        })
+        .collect();
+
+    for len in [0, 1, 63, 64, 65, 127, 128, 129, 1000, 10_000] {
+        let data = &data[..len];
+        let expected = naive_structural(data, b';');
+
+        for indexer in [StructuralIndexer::new(b';', b'"'), StructuralIndexer::scalar(b';', b'"')] {
+            let mut positions = Vec::new();
+            let mut in_quotes = false;
+            indexer.index(data, &mut in_quotes, &mut positions);
+            assert_eq!((positions, in_quotes), expected, "len {}", len);
+            assert_eq!(
+                indexer.count_delimiters(data),
+                expected.0.iter().filter(|&&p| data[p] == b';').count()
+            );
+        }
+    }
+}
+
+#[test]
+fn structural_indexer_carries_the_quote_state() {
+    let indexer = StructuralIndexer::new(b',', b'"');
+    let mut positions = Vec::new();
+
+    let mut in_quotes = true;
+    indexer.index(b"a,b\",c", &mut in_quotes, &mut positions);
+    assert_eq!(positions, vec![4]);
+    assert!(!in_quotes);
+
+    assert_eq!(indexer.fields(b"1,\"a,b\",3"), vec![0..1, 2..7, 8..9]);
+}
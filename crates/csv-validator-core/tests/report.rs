@@ -0,0 +1,47 @@
+use csv_validator_core::{ValidatorSpec, ValidationOptions, validate_file};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+#[test]
+fn report_counts_records_issues_and_columns() {
+    let path = "tests/data/bad.csv";
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_illegal_chars(vec!["o".to_string(), "@@".to_string()]).into_validator(b',').unwrap(),
+        ValidatorSpec::new_field_count(3).into_validator(b',').unwrap(),
+    ]);
+    let options = ValidationOptions { threads: 2, batch_size: 3, has_header: true, ..Default::default() };
+
+    let outcome = validate_file(path, validators, options).expect("validation failed");
+    let report = outcome.report;
+
+    assert_eq!(report.records, 7);
+    assert_eq!(report.bytes, std::fs::metadata(path).unwrap().len());
+    assert_eq!(report.columns, vec!["id", "name", "comment"]);
+    assert_eq!(report.column_name(2), Some("comment"));
+    assert!(!report.truncated);
+
+    let mut per_validator = BTreeMap::new();
+    for issue in &outcome.issues {
+        *per_validator.entry(issue.validator).or_default() += 1;
+    }
+    assert_eq!(report.issues_per_validator, per_validator);
+    assert_eq!(report.total_issues(), outcome.issues.len() as u64);
+    assert_eq!(report.issues_per_column, BTreeMap::from([(1, 3), (2, 14)]));
+
+    let mut lines_with_issues: Vec<_> = outcome.issues.iter().map(|i| i.line_number).filter(|&line| line > 1).collect();
+    lines_with_issues.dedup();
+    assert_eq!(report.records_with_issues, lines_with_issues.len() as u64);
+    assert_eq!(report.issue_rate(), lines_with_issues.len() as f64 / 7.0);
+}
+
+#[test]
+fn issues_know_their_column() {
+    let validators = Arc::new(vec![ValidatorSpec::new_illegal_chars(vec!["!".to_string()]).into_validator(b';').unwrap()]);
+    let options = ValidationOptions { separator: b';', ..Default::default() };
+
+    let outcome = csv_validator_core::validate_reader(&b"a!;\"b;!\";c\n!"[..], validators, options).expect("validation failed");
+    let columns: Vec<_> = outcome.issues.iter().map(|i| (i.line_number, i.column)).collect();
+
+    assert_eq!(columns, vec![(1, Some(0)), (1, Some(1)), (2, Some(0))]);
+}
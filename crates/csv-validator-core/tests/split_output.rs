@@ -0,0 +1,119 @@
+use csv_validator_core::{FieldCountRepair, ValidatorSpec, ValidationOptions, validate_file};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+
+mod common;
+use common::{path, temp_dir};
+
+#[test]
+fn split_into_clean_and_rejects_in_original_order() {
+    let dir = temp_dir("split");
+    let input = path(&dir, "input.csv");
+
+    let mut data = String::from("id,name\n");
+    let mut clean = data.clone();
+    let mut rejects = String::from("id,name,line_number,issues\n");
+    for i in 1..=500 {
+        let line_number = i + 1;
+        if i % 7 == 0 {
+            data.push_str(&format!("{},bad@name,\"x\"\n", i));
+            rejects.push_str(&format!(
+                "{},bad@name,\"x\",{},\"Illegal character(s) '@' | Expected 2 fields, found 3\"\n",
+                i, line_number
+            ));
+        } else {
+            data.push_str(&format!("{},name\n", i));
+            clean.push_str(&format!("{},name\n", i));
+        }
+    }
+    std::fs::write(&input, &data).unwrap();
+
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_illegal_chars(vec!["@".to_string()]).into_validator(b',').unwrap(),
+        ValidatorSpec::new_field_count(2).into_validator(b',').unwrap(),
+    ]);
+
+    let options = ValidationOptions {
+        threads: 4,
+        batch_size: 16,
+        has_header: true,
+        clean_output: Some(path(&dir, "clean.csv")),
+        reject_output: Some(path(&dir, "rejects.csv")),
+        ..Default::default()
+    };
+
+    let issues = validate_file(&input, validators, options).expect("validation failed").issues;
+
+    assert_eq!(issues.len(), 2 * (500 / 7));
+    assert_eq!(std::fs::read_to_string(dir.join("clean.csv")).unwrap(), clean);
+    assert_eq!(std::fs::read_to_string(dir.join("rejects.csv")).unwrap(), rejects);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fix_mode_rejects_what_it_cannot_fix() {
+    let dir = temp_dir("fix-split");
+    let input = path(&dir, "input.csv");
+    std::fs::write(&input, "a;b;c\n1;2\n1;2;3;4\n").unwrap();
+
+    let validators = Arc::new(vec![
+        ValidatorSpec::new_field_count_with_repair(3, FieldCountRepair::Pad).into_validator(b';').unwrap(),
+    ]);
+
+    let options = ValidationOptions {
+        separator: b';',
+        has_header: true,
+        fix_output: Some(path(&dir, "fixed.csv")),
+        reject_output: Some(path(&dir, "rejects.csv")),
+        ..Default::default()
+    };
+
+    validate_file(&input, validators, options).expect("validation failed");
+
+    assert_eq!(std::fs::read_to_string(dir.join("fixed.csv")).unwrap(), "a;b;c\n1;2;\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("rejects.csv")).unwrap(),
+        "a;b;c;line_number;issues\n1;2;3;4;3;\"Expected 3 fields, found 4\"\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fix_and_clean_output_are_exclusive() {
+    let options = ValidationOptions {
+        fix_output: Some("fixed.csv".to_string()),
+        clean_output: Some("clean.csv".to_string()),
+        ..Default::default()
+    };
+
+    let result = csv_validator_core::validate_reader(&b"a,b\n"[..], Arc::new(Vec::new()), options);
+    assert!(result.is_err());
+}
+
+#[test]
+fn crlf_lines_keep_their_line_end() {
+    let dir = temp_dir("crlf");
+    let input = path(&dir, "input.csv");
+    std::fs::write(&input, "a,b\r\n1,2\r\n1,2,3\r\n").unwrap();
+
+    let validators = Arc::new(vec![ValidatorSpec::new_field_count(2).into_validator(b',').unwrap()]);
+    let options = ValidationOptions {
+        has_header: true,
+        clean_output: Some(path(&dir, "clean.csv")),
+        reject_output: Some(path(&dir, "rejects.csv")),
+        ..Default::default()
+    };
+
+    let report = validate_file(&input, validators, options).expect("validation failed").report;
+
+    assert_eq!(report.columns, vec!["a", "b"]);
+    assert_eq!(std::fs::read_to_string(dir.join("clean.csv")).unwrap(), "a,b\r\n1,2\r\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("rejects.csv")).unwrap(),
+        "a,b,line_number,issues\r\n1,2,3,3,\"Expected 2 fields, found 3\"\r\n"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,86 @@
+use csv_validator_core::{ValidationIssue, ValidationOptions, Validator, validate_reader};
+use rayon::ThreadPoolBuilder;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Records the name of every thread it runs on.
+#[derive(Clone, Default)]
+struct ThreadNameValidator {
+    threads: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Validator for ThreadNameValidator {
+    fn validate(&self, _line: &[u8], _line_number: usize, _issues: &mut Vec<ValidationIssue>) {
+        let name = std::thread::current().name().unwrap_or("<unnamed>").to_string();
+        self.threads.lock().unwrap().insert(name);
+    }
+
+    fn name(&self) -> &'static str {
+        "thread_name"
+    }
+
+    fn clone_box(&self) -> Box<dyn Validator> {
+        Box::new(self.clone())
+    }
+}
+
+fn input() -> Vec<u8> {
+    (0..2_000).map(|i| format!("{},a,b\n", i)).collect::<String>().into_bytes()
+}
+
+fn run_in_pool(prefix: &'static str, threads: usize) -> HashSet<String> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(move |i| format!("{}-{}", prefix, i))
+        .build()
+        .unwrap();
+
+    let validator = ThreadNameValidator::default();
+    let seen = validator.threads.clone();
+    let options = ValidationOptions {
+        thread_pool: Some(Arc::new(pool)),
+        batch_size: 10,
+        ..Default::default()
+    };
+
+    validate_reader(&input()[..], Arc::new(vec![Box::new(validator) as Box<dyn Validator>]), options)
+        .expect("validation failed");
+
+    seen.lock().unwrap().clone()
+}
+
+#[test]
+fn validation_runs_in_the_given_pool() {
+    let seen = run_in_pool("caller-pool", 2);
+
+    assert!(!seen.is_empty());
+    assert!(seen.iter().all(|name| name.starts_with("caller-pool-")), "{:?}", seen);
+}
+
+#[test]
+fn threads_option_limits_the_pool_size() {
+    let validator = ThreadNameValidator::default();
+    let seen = validator.threads.clone();
+    let options = ValidationOptions {
+        threads: 2,
+        batch_size: 10,
+        ..Default::default()
+    };
+
+    validate_reader(&input()[..], Arc::new(vec![Box::new(validator) as Box<dyn Validator>]), options)
+        .expect("validation failed");
+
+    assert!(seen.lock().unwrap().len() <= 2);
+}
+
+#[test]
+fn concurrent_validations_do_not_share_pools() {
+    let first = std::thread::spawn(|| run_in_pool("first", 3));
+    let second = std::thread::spawn(|| run_in_pool("second", 3));
+
+    let first = first.join().unwrap();
+    let second = second.join().unwrap();
+
+    assert!(first.iter().all(|name| name.starts_with("first-")), "{:?}", first);
+    assert!(second.iter().all(|name| name.starts_with("second-")), "{:?}", second);
+}
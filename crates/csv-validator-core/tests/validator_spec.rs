@@ -0,0 +1,64 @@
+use csv_validator_core::{Dialect, FieldCountRepair, MergeColumn, Severity, ValidatorSpec, into_validators};
+use pretty_assertions::assert_eq;
+
+#[test]
+fn specs_are_read_from_yaml() {
+    let yaml = r#"
+- type: illegal_chars
+  illegal_chars: ['@', '!']
+  replace_with: ['_', '.']
+- type: field_count
+  expected: 3
+  repair: merge:1
+  severity: warning
+- type: line_length
+  max_length: 80
+  enabled: false
+"#;
+    let specs: Vec<ValidatorSpec> = serde_yaml::from_str(yaml).unwrap();
+
+    assert_eq!(
+        specs,
+        vec![
+            ValidatorSpec::new_illegal_chars_with_replacements(
+                vec!["@".to_string(), "!".to_string()],
+                vec!["_".to_string(), ".".to_string()]
+            ),
+            ValidatorSpec::new_field_count_with_repair(3, FieldCountRepair::Merge(MergeColumn::Index(1))).with_severity(Severity::Warning),
+            ValidatorSpec::LineLength { id: None, enabled: false, max_length: 80, severity: Severity::Error, common: None },
+        ]
+    );
+    assert_eq!(serde_yaml::from_str::<Vec<ValidatorSpec>>(&serde_yaml::to_string(&specs).unwrap()).unwrap(), specs);
+}
+
+#[test]
+fn unknown_keys_and_bad_values_are_rejected() {
+    for yaml in [
+        "{type: field_count, expected: 3, expect: 4}",
+        "{type: field_count, expected: 3, repair: squash}",
+        "{type: line_length, max_length: 80, severity: fatal}",
+        "{type: column_type, column: 1}",
+    ] {
+        assert!(serde_yaml::from_str::<ValidatorSpec>(yaml).is_err(), "{}", yaml);
+    }
+}
+
+#[test]
+fn disabled_specs_are_left_out() {
+    let specs = vec![
+        ValidatorSpec::new_field_count(3),
+        ValidatorSpec::LineLength { id: None, enabled: false, max_length: 80, severity: Severity::Error, common: None },
+        ValidatorSpec::new_illegal_chars(vec!["@".to_string()]),
+    ];
+
+    let names: Vec<_> = into_validators(specs, &Dialect::default()).unwrap().iter().map(|v| v.name()).collect();
+    assert_eq!(names, vec!["field_count", "illegal_characters"]);
+}
+
+#[test]
+fn deprecated_fix_is_still_read() {
+    let spec: ValidatorSpec = serde_yaml::from_str("{type: illegal_chars, illegal_chars: ['@'], fix: false}").unwrap();
+
+    assert_eq!(spec.type_name(), "illegal_chars");
+    assert!(spec.into_validator(b',').is_ok());
+}
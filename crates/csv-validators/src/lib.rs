@@ -1,10 +1,9 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use pyo3::prelude::*;
-use pyo3::types::{PyModule};
-use csv_validator_core::{ValidatorSpec, ValidationOptions, validate_file,ValidationIssue};
-use pyo3::prelude::*;
-use pyo3::types::PyList;
-use pyo3::FromPyObject;
+use pyo3::types::{PyDict, PyIterator, PyList, PyModule};
+use csv_validator_core::{CancellationToken, CommonSettings, Dialect, Error, FieldCountRepair, IssueParam, Progress, Severity, ValidatorSpec, ValidationOptions, ValidationReport, into_validators, validate_file, validate_file_with_sink, ValidationIssue};
 
 
 #[pyclass]
@@ -15,37 +14,72 @@ pub struct PyValidatorSpec {
 
 #[pymethods]
 impl PyValidatorSpec {
+    /// severity: "error" (default), "warning" or "info"
     #[staticmethod]
-    pub fn illegal_chars(chars: Vec<String>) -> Self {
-        Self {
-            inner: ValidatorSpec::IllegalChars {
-                illegal_chars: chars,
-                enabled: true,
-            },
+    #[pyo3(signature = (chars, replace_with=None, severity=None))]
+    pub fn illegal_chars(chars: Vec<String>, replace_with: Option<Vec<String>>, severity: Option<&str>) -> PyResult<Self> {
+        let replace_with = replace_with.unwrap_or_default();
+        if !replace_with.is_empty() && replace_with.len() != chars.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "chars and replace_with must have the same length",
+            ));
         }
+        Ok(Self {
+            inner: ValidatorSpec::new_illegal_chars_with_replacements(chars, replace_with).with_severity(parse_severity(severity)?),
+        })
     }
 
+    /// repair: "pad", "truncate", "reject" or "merge:<column name or index>"
     #[staticmethod]
-    pub fn field_count(expected: usize) -> Self {
-        Self {
+    #[pyo3(signature = (expected, repair=None, severity=None))]
+    pub fn field_count(expected: usize, repair: Option<&str>, severity: Option<&str>) -> PyResult<Self> {
+        let repair = repair
+            .map(str::parse::<FieldCountRepair>)
+            .transpose()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(Self {
             inner: ValidatorSpec::FieldCount {
+                id: None,
                 expected,
+                repair,
                 enabled: true,
+                severity: parse_severity(severity)?,
+                common: None,
             },
-        }
+        })
     }
 
+    /// Any validator, written as in a config file: {"type": "field_count", "expected": 3, "severity": "warning"}
     #[staticmethod]
-    pub fn line_length(max_length: usize) -> Self {
-        Self {
+    pub fn from_dict(py: Python<'_>, spec: &Bound<'_, PyDict>) -> PyResult<Self> {
+        let json: String = py.import("json")?.call_method1("dumps", (spec,))?.extract()?;
+        let inner = serde_json::from_str(&json).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (max_length, severity=None))]
+    pub fn line_length(max_length: usize, severity: Option<&str>) -> PyResult<Self> {
+        Ok(Self {
             inner: ValidatorSpec::LineLength {
+                id: None,
                 max_length,
                 enabled: true,
+                severity: parse_severity(severity)?,
+                common: None,
             },
-        }
+        })
     }
 }
 
+fn parse_severity(severity: Option<&str>) -> PyResult<Severity> {
+    severity
+        .map(str::parse::<Severity>)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
 #[pyclass]
 #[derive(Clone)]
 pub struct PyValidationOptions {
@@ -55,6 +89,37 @@ pub struct PyValidationOptions {
     pub batch_size: usize,
     #[pyo3(get, set)]
     pub buffer_size: usize,
+    #[pyo3(get, set)]
+    pub mmap: bool,
+    #[pyo3(get, set)]
+    pub max_in_flight: Option<usize>,
+    /// The options below override the `common` settings of the validators, None to take theirs, or the default:
+    /// ",", '"', no header and no rows skipped.
+    #[pyo3(get, set)]
+    pub separator: Option<char>,
+    #[pyo3(get, set)]
+    pub quote_char: Option<char>,
+    #[pyo3(get, set)]
+    pub has_header: Option<bool>,
+    #[pyo3(get, set)]
+    pub skip_rows: Option<usize>,
+    #[pyo3(get, set)]
+    pub preserve_order: bool,
+    #[pyo3(get, set)]
+    pub fix_output: Option<String>,
+    #[pyo3(get, set)]
+    pub clean_output: Option<String>,
+    #[pyo3(get, set)]
+    pub reject_output: Option<String>,
+    #[pyo3(get, set)]
+    pub max_issues: Option<usize>,
+    #[pyo3(get, set)]
+    pub max_issues_per_validator: Option<usize>,
+    #[pyo3(get, set)]
+    pub fail_fast: bool,
+    /// seconds
+    #[pyo3(get, set)]
+    pub timeout: Option<f64>,
 }
 
 #[pymethods]
@@ -65,6 +130,20 @@ impl PyValidationOptions {
             threads: num_cpus::get(),
             batch_size: 100_000,
             buffer_size: 8 * 1024 * 1024,
+            mmap: false,
+            max_in_flight: None,
+            separator: None,
+            quote_char: None,
+            has_header: None,
+            skip_rows: None,
+            preserve_order: true,
+            fix_output: None,
+            clean_output: None,
+            reject_output: None,
+            max_issues: None,
+            max_issues_per_validator: None,
+            fail_fast: false,
+            timeout: None,
         }
     }
 }
@@ -74,63 +153,339 @@ impl Default for PyValidationOptions {
             threads: num_cpus::get(),
             batch_size: 100_000,
             buffer_size: 8 * 1024 * 1024,
+            mmap: false,
+            max_in_flight: None,
+            separator: None,
+            quote_char: None,
+            has_header: None,
+            skip_rows: None,
+            preserve_order: true,
+            fix_output: None,
+            clean_output: None,
+            reject_output: None,
+            max_issues: None,
+            max_issues_per_validator: None,
+            fail_fast: false,
+            timeout: None,
         }
     }
 }
 
 
-impl From<&PyValidationOptions> for ValidationOptions {
-    fn from(py: &PyValidationOptions) -> Self {
-        Self {
-            threads: py.threads,
-            batch_size: py.batch_size,
-            buffer_size: py.buffer_size,
+impl PyValidationOptions {
+    /// The settings that override the validators' own `common` settings, see `Dialect::resolve`.
+    fn common(&self) -> CommonSettings {
+        CommonSettings {
+            separator: self.separator,
+            quote_char: self.quote_char,
+            has_header: self.has_header,
+            skip_rows: self.skip_rows,
+        }
+    }
+
+    /// The engine options, for a file in `dialect`.
+    fn to_options(&self, dialect: &Dialect) -> ValidationOptions {
+        ValidationOptions {
+            threads: self.threads,
+            thread_pool: None,
+            batch_size: self.batch_size,
+            buffer_size: self.buffer_size,
+            mmap: self.mmap,
+            max_in_flight: self.max_in_flight,
+            separator: dialect.separator,
+            quote_char: dialect.quote_char,
+            has_header: dialect.has_header,
+            skip_rows: dialect.skip_rows,
+            preserve_order: self.preserve_order,
+            fix_output: self.fix_output.clone(),
+            clean_output: self.clean_output.clone(),
+            reject_output: self.reject_output.clone(),
+            max_issues: self.max_issues,
+            max_issues_per_validator: self.max_issues_per_validator,
+            fail_fast: self.fail_fast,
+            cancellation: None,
+            timeout: self.timeout.map(Duration::from_secs_f64),
+            on_progress: None,
         }
     }
 }
 
 #[pyclass]
+#[derive(Clone)]
 pub struct PyValidationIssue {
     #[pyo3(get)]
     pub validator: String,
+    /// stable code, ie. "FC001"
+    #[pyo3(get)]
+    pub code: String,
+    /// stable name of the code, ie. "field_count_mismatch"
+    #[pyo3(get)]
+    pub code_name: String,
+    /// "error", "warning" or "info"
+    #[pyo3(get)]
+    pub severity: String,
     #[pyo3(get)]
     pub line_number: usize,
     #[pyo3(get)]
     pub position: Option<usize>,
     #[pyo3(get)]
+    pub column: Option<usize>,
+    #[pyo3(get)]
     pub message: String,
+    /// the values the message is built from, ie. {"expected": 3, "found": 4}
+    #[pyo3(get)]
+    pub params: HashMap<String, PyIssueParam>,
+    #[pyo3(get)]
+    pub status: String,
+}
+
+/// An int or a str on the python side.
+#[derive(Clone, IntoPyObject)]
+pub enum PyIssueParam {
+    Number(u64),
+    Text(String),
+}
+
+impl From<IssueParam> for PyIssueParam {
+    fn from(param: IssueParam) -> Self {
+        match param {
+            IssueParam::Number(value) => PyIssueParam::Number(value),
+            IssueParam::Text(value) => PyIssueParam::Text(value),
+        }
+    }
 }
 
 impl From<ValidationIssue> for PyValidationIssue {
     fn from(issue: ValidationIssue) -> Self {
         Self {
             validator: issue.validator.parse().unwrap(),
+            code: issue.code.code.to_string(),
+            code_name: issue.code.name.to_string(),
+            severity: issue.severity.as_str().to_string(),
             line_number: issue.line_number,
             position: issue.position,
+            column: issue.column,
             message: issue.message,
+            params: issue.params.into_iter().map(|(name, value)| (name.to_string(), value.into())).collect(),
+            status: issue.status.as_str().to_string(),
+        }
+    }
+}
+
+/// Passed to the `on_progress` callable of `validate_file_py`.
+#[pyclass]
+#[derive(Clone)]
+pub struct PyProgress {
+    #[pyo3(get)]
+    pub bytes: u64,
+    /// None when the input size isn't known
+    #[pyo3(get)]
+    pub total_bytes: Option<u64>,
+    #[pyo3(get)]
+    pub records: u64,
+    #[pyo3(get)]
+    pub issues: u64,
+    /// seconds
+    #[pyo3(get)]
+    pub elapsed: f64,
+    /// bytes per second
+    #[pyo3(get)]
+    pub throughput: f64,
+    /// share of the input done, between 0 and 1, None when the input size isn't known
+    #[pyo3(get)]
+    pub fraction: Option<f64>,
+    #[pyo3(get)]
+    pub finished: bool,
+}
+
+impl From<&Progress> for PyProgress {
+    fn from(progress: &Progress) -> Self {
+        Self {
+            bytes: progress.bytes,
+            total_bytes: progress.total_bytes,
+            records: progress.records,
+            issues: progress.issues,
+            elapsed: progress.elapsed.as_secs_f64(),
+            throughput: progress.throughput(),
+            fraction: progress.fraction(),
+            finished: progress.finished,
         }
     }
 }
 
+/// The issues found, iterable like a list of issues, and the summary of the run.
+#[pyclass]
+pub struct PyValidationReport {
+    #[pyo3(get)]
+    pub issues: Vec<PyValidationIssue>,
+    /// records validated, the header not included
+    #[pyo3(get)]
+    pub records: u64,
+    #[pyo3(get)]
+    pub bytes: u64,
+    #[pyo3(get)]
+    pub records_with_issues: u64,
+    #[pyo3(get)]
+    pub issues_per_validator: HashMap<String, u64>,
+    /// zero-based column index -> issues
+    #[pyo3(get)]
+    pub issues_per_column: HashMap<usize, u64>,
+    /// column names from the header, when has_header is set
+    #[pyo3(get)]
+    pub columns: Vec<String>,
+    /// seconds
+    #[pyo3(get)]
+    pub elapsed: f64,
+    /// bytes per second
+    #[pyo3(get)]
+    pub throughput: f64,
+    /// share of the records with issues, between 0 and 1
+    #[pyo3(get)]
+    pub issue_rate: f64,
+    /// a limit (max_issues, max_issues_per_validator, fail_fast) stopped the validation early
+    #[pyo3(get)]
+    pub truncated: bool,
+}
+
+impl PyValidationReport {
+    fn new(issues: Vec<ValidationIssue>, report: ValidationReport) -> Self {
+        Self {
+            issues: issues.into_iter().map(Into::into).collect(),
+            records: report.records,
+            bytes: report.bytes,
+            records_with_issues: report.records_with_issues,
+            issues_per_validator: report
+                .issues_per_validator
+                .iter()
+                .map(|(validator, count)| (validator.to_string(), *count))
+                .collect(),
+            issues_per_column: report.issues_per_column.clone().into_iter().collect(),
+            elapsed: report.elapsed.as_secs_f64(),
+            throughput: report.throughput(),
+            issue_rate: report.issue_rate(),
+            truncated: report.truncated,
+            columns: report.columns,
+        }
+    }
+}
+
+#[pymethods]
+impl PyValidationReport {
+    fn __len__(&self) -> usize {
+        self.issues.len()
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new(py, self.issues.clone())?.try_iter()
+    }
+}
+
+/// With `on_issues`, issues are passed to that callable batch by batch as the validation progresses,
+/// and the returned report holds no issues.
+/// `on_progress` is called with a `PyProgress` as batches complete, at most every 100ms, and once more at the end.
+/// The validation runs without holding the GIL, and Ctrl-C cancels it with a KeyboardInterrupt.
 #[pyfunction]
+#[pyo3(signature = (path, validators, options=None, on_issues=None, on_progress=None))]
 pub fn validate_file_py(
     py: Python<'_>,
     path: &str,
     validators: Vec<PyValidatorSpec>,
     options: Option<PyObject>,
-) -> PyResult<Vec<PyValidationIssue>> {
+    on_issues: Option<PyObject>,
+    on_progress: Option<PyObject>,
+) -> PyResult<PyValidationReport> {
     let opts: PyValidationOptions = match options {
         Some(obj) => obj.extract(py)?,
         None => PyValidationOptions::default(),
     };
 
-    let specs: Vec<_> = validators
-        .into_iter()
-        .map(|v| v.inner.into_validator(b',')) // TODO: make separator configurable
-        .collect();
+    // the options over the validators' common settings, like the command line flags over a config file
+    let specs: Vec<ValidatorSpec> = validators.into_iter().map(|v| v.inner).collect();
+    let dialect = Dialect::resolve(&opts.common(), &CommonSettings::default(), &specs).map_err(to_py_err)?;
+    let validators = into_validators(specs, &dialect).map_err(to_py_err)?;
+
+    let cancellation = CancellationToken::new();
+    let mut options = opts.to_options(&dialect);
+    options.cancellation = Some(cancellation.clone());
+    let path = path.to_string();
+
+    // a python exception raised by the progress callback cancels the validation, and is re-raised as is
+    let progress_error = Arc::new(Mutex::new(None));
+    if let Some(on_progress) = on_progress {
+        let progress_error = progress_error.clone();
+        let cancellation = cancellation.clone();
+        options.on_progress = Some(Arc::new(move |progress: &Progress| {
+            let progress = PyProgress::from(progress);
+            if let Err(err) = Python::with_gil(|py| on_progress.call1(py, (progress,))) {
+                *progress_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(err);
+                cancellation.cancel();
+            }
+        }));
+    }
+
+    // the validation runs on its own thread, so this one can watch for Ctrl-C meanwhile
+    let caller = std::thread::current();
+    let validation = std::thread::spawn(move || {
+        let result = run_validation(&path, Arc::new(validators), options, on_issues);
+        caller.unpark();
+        result
+    });
+
+    while !validation.is_finished() {
+        py.allow_threads(|| std::thread::park_timeout(Duration::from_millis(100)));
+        if let Err(interrupt) = py.check_signals() {
+            cancellation.cancel();
+            let _ = py.allow_threads(|| validation.join());
+            return Err(interrupt);
+        }
+    }
+
+    let result = py.allow_threads(|| validation.join())
+        .map_err(|_| pyo3::exceptions::PyRuntimeError::new_err("validation thread panicked"))?;
+    if let Some(err) = progress_error.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        return Err(err);
+    }
+    result
+}
+
+fn run_validation(
+    path: &str,
+    validators: Arc<Vec<Box<dyn csv_validator_core::Validator>>>,
+    options: ValidationOptions,
+    on_issues: Option<PyObject>,
+) -> PyResult<PyValidationReport> {
+    let Some(on_issues) = on_issues else {
+        let outcome = validate_file(path, validators, options).map_err(to_py_err)?;
+        return Ok(PyValidationReport::new(outcome.issues, outcome.report));
+    };
+
+    // a python exception raised by the callback stops the validation and is re-raised as is
+    let mut callback_error = None;
+    let mut sink = |issues: Vec<ValidationIssue>| -> std::io::Result<()> {
+        let batch: Vec<PyValidationIssue> = issues.into_iter().map(Into::into).collect();
+        Python::with_gil(|py| on_issues.call1(py, (batch,)).map(|_| ())).map_err(|err| {
+            callback_error = Some(err);
+            std::io::Error::other("on_issues callback raised an exception")
+        })
+    };
+
+    let result = validate_file_with_sink(path, validators, options, &mut sink);
+    if let Some(err) = callback_error {
+        return Err(err);
+    }
+    Ok(PyValidationReport::new(Vec::new(), result.map_err(to_py_err)?))
+}
 
-    let issues = validate_file(path, Arc::new(specs), (&opts).into())?;
-    Ok(issues.into_iter().map(Into::into).collect())
+/// IO errors become the matching OSError, bad settings a ValueError.
+fn to_py_err(err: Error) -> PyErr {
+    use pyo3::exceptions::{PyRuntimeError, PyTimeoutError, PyUnicodeError, PyValueError};
+    match err {
+        Error::Io(err) => err.into(),
+        Error::Config(_) | Error::Pattern(_) => PyValueError::new_err(err.to_string()),
+        Error::Encoding { .. } => PyUnicodeError::new_err(err.to_string()),
+        Error::TimedOut(_) => PyTimeoutError::new_err(err.to_string()),
+        Error::ThreadPool(_) | Error::Cancelled => PyRuntimeError::new_err(err.to_string()),
+    }
 }
 
 // #[pyfunction]
@@ -138,7 +493,7 @@ pub fn validate_file_py(
 //     lines: &PyList,
 //     validators: Vec<PyValidatorSpec>,
 //     options: Option<PyValidationOptions>,
-// ) -> PyResult<Vec<PyValidationIssue>> {
+// ) -> PyResult<PyValidationReport> {
 //     let lines: Vec<(usize, Vec<u8>)> = lines
 //         .iter()
 //         .enumerate()
@@ -160,7 +515,7 @@ pub fn validate_file_py(
 
 
 #[pyfunction]
-pub fn jahallo(py: Python) -> PyResult<String>{
+pub fn jahallo(_py: Python) -> PyResult<String>{
     Ok("jahallo".to_string())
 }
 
@@ -171,6 +526,8 @@ fn csv_validators(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyValidatorSpec>()?;
     m.add_class::<PyValidationIssue>()?;
     m.add_class::<PyValidationOptions>()?;
+    m.add_class::<PyValidationReport>()?;
+    m.add_class::<PyProgress>()?;
     m.add_function(wrap_pyfunction!(validate_file_py, m)?)?;
     Ok(())
 }
\ No newline at end of file